@@ -1,38 +1,168 @@
+use crate::archive::SqliteArchive;
+use crate::backup::{read_backups, BackupArchive};
+use crate::journal::{Journal, JournalAction, JournalResult};
 use crate::models::UnifiedItem;
-use roux::Me;
-use roux::util::RouxError;
+use crate::protect::ProtectList;
+use crate::api::RedditApi;
+use crate::cli::OutputFormat;
 use std::error::Error;
 use std::io::{self, Write};
-use csv;
-use crate::utils::escape_csv_field;
+use std::path::Path;
+use crate::utils::{escape_csv_field, format_timestamp_iso};
+use serde::Serialize;
+use std::fs::File;
+use std::io::BufWriter;
 
+/// Run-wide options and record keeping shared by the overwrite and delete actions.
+pub struct ActionContext {
+    pub skip_confirmation: bool,
+    pub dry_run: bool,
+    /// Progress journal used to resume interrupted runs.
+    pub journal: Option<Journal>,
+    /// Archive that receives every item right before it is changed.
+    pub backup: Option<BackupArchive>,
+    /// SQLite archive (`--sqlite`) whose rows are marked as items are changed.
+    pub archive: Option<SqliteArchive>,
+    /// Items no action may change.
+    pub protect: ProtectList,
+    pub debug_mode: bool,
+}
+
+/// Replace the content of every item with `overwrite_text`. Returns the number of items changed.
 pub async fn handle_overwrite_action(
-    reddit: &Me,
-    items: &mut Vec<UnifiedItem>,
+    api: &RedditApi,
+    items: &mut [UnifiedItem],
     overwrite_text: &str,
-    debug_mode: bool,
-) -> Result<(), Box<dyn Error>> {
+    ctx: &mut ActionContext,
+) -> Result<usize, Box<dyn Error>> {
+    let dry_run = ctx.dry_run;
+    let debug_mode = ctx.debug_mode;
+    if dry_run {
+        println!("\n--- Dry run: overwrite (no changes will be made) ---");
+        let mut protected_count = 0;
+        let mut message_count = 0;
+        let mut archived_count = 0;
+        for item in items.iter() {
+            match overwrite_skip(item, &ctx.protect) {
+                Some(OverwriteSkip::Protected(reason)) => {
+                    println!("Protected, skipping {} {} ({})", item.item_type.to_lowercase(), item.id, reason);
+                    protected_count += 1;
+                    continue;
+                }
+                Some(OverwriteSkip::Message) => {
+                    println!("Skipping message {} (messages cannot be overwritten)", item.id);
+                    message_count += 1;
+                    continue;
+                }
+                Some(OverwriteSkip::Archived) => {
+                    println!("Archived, skipping {} {} (archived items cannot be edited, only deleted with --delete)", item.item_type.to_lowercase(), item.id);
+                    archived_count += 1;
+                    continue;
+                }
+                None => {}
+            }
+            print_dry_run_item("overwrite", item);
+            println!("    Old content: {}", preview_text(&item.content));
+            println!("    New content: {}", preview_text(overwrite_text));
+        }
+        println!("\n--- Overwrite Summary (dry run) ---");
+        println!("Would overwrite:        {} items", items.len() - protected_count - message_count - archived_count);
+        if message_count > 0 {
+            println!("Skipped (messages):     {} items", message_count);
+        }
+        if archived_count > 0 {
+            println!("Skipped (archived):     {} items", archived_count);
+        }
+        if !ctx.protect.is_empty() {
+            println!("Protected (skipped):    {} items", protected_count);
+        }
+        return Ok(0);
+    }
+
     if debug_mode {
         println!("\n--- Overwriting content for filtered items ---");
     }
     let mut overwrite_success_count = 0;
     let mut overwrite_fail_count = 0;
+    let mut skipped_message_count = 0;
+    let mut protected_count = 0;
+    let mut skipped_archived_count = 0;
+    let retries_before = api.retries();
+    // Skipped items are left out of the journal, so --resume does not retry them
+    let editable: Vec<UnifiedItem> =
+        items.iter().filter(|item| overwrite_skip(item, &ctx.protect).is_none()).cloned().collect();
+    let already_done_count = record_pending(ctx.journal.as_mut(), JournalAction::Overwrite, &editable)?;
 
     for item in items.iter_mut() {
+        match overwrite_skip(item, &ctx.protect) {
+            Some(OverwriteSkip::Protected(reason)) => {
+                if debug_mode {
+                    println!("Skipping protected item ID {} ({})", item.id, reason);
+                }
+                protected_count += 1;
+                continue;
+            }
+            Some(OverwriteSkip::Message) => {
+                if debug_mode {
+                    println!("Skipping message ID {} (messages cannot be overwritten)", item.id);
+                }
+                skipped_message_count += 1;
+                continue;
+            }
+            Some(OverwriteSkip::Archived) => {
+                // Archived items can only be deleted, which --delete still does
+                if debug_mode {
+                    println!("Skipping archived item ID {} (archived items cannot be edited, only deleted with --delete)", item.id);
+                }
+                skipped_archived_count += 1;
+                continue;
+            }
+            None => {}
+        }
+        if ctx.journal.as_ref().is_some_and(|j| j.is_done(JournalAction::Overwrite, &item.id)) {
+            if debug_mode {
+                println!("Skipping item ID {} (already overwritten according to journal)", item.id);
+            }
+            continue;
+        }
         if debug_mode {
             println!("Attempting to overwrite item ID: {}", item.id);
         }
 
+        if let Some(backup) = ctx.backup.as_mut() {
+            backup.append(item, JournalAction::Overwrite)?;
+        }
+
         let success: bool;
-        match reddit.edit(overwrite_text, &item.id).await {
-            Ok(_response) => {
+        let params = [("thing_id", item.id.as_str()), ("text", overwrite_text)];
+        match api.post_form("api/editusertext", &params).await {
+            Ok(response) if response.status().is_success() => {
+                let _ = response.text().await;
                 println!("Successfully overwrote {}: {}", item.item_type.to_lowercase(), item.id);
                 item.content = overwrite_text.to_string();
+                record_result(ctx.journal.as_mut(), JournalAction::Overwrite, &item.id, None)?;
+                if let Some(archive) = ctx.archive.as_mut() {
+                    archive.mark(&item.id, JournalAction::Overwrite)?;
+                }
                 success = true;
             }
+            Ok(response) => {
+                let status = response.status();
+                let error_body = response.text().await.unwrap_or_else(|e| format!("Could not read error response body: {}", e));
+                eprintln!(
+                    "Failed to overwrite {} {} - API Error Status: {}. Details: {}",
+                    item.item_type.to_lowercase(),
+                    item.id,
+                    status,
+                    error_body
+                );
+                record_result(ctx.journal.as_mut(), JournalAction::Overwrite, &item.id, Some(status.to_string()))?;
+                success = false;
+            }
             Err(e) => {
                 eprintln!("Failed to overwrite {} {}: {}", item.item_type.to_lowercase(), item.id, e);
-                if debug_mode { eprintln!("Debug details for RouxError: {:?}", e); }
+                if debug_mode { eprintln!("Debug details for request error: {:?}", e); }
+                record_result(ctx.journal.as_mut(), JournalAction::Overwrite, &item.id, Some(e.to_string()))?;
                 success = false;
             }
         }
@@ -43,74 +173,103 @@ pub async fn handle_overwrite_action(
             overwrite_fail_count += 1;
         }
     }
-    if debug_mode || overwrite_success_count > 0 || overwrite_fail_count > 0 {
+    if debug_mode || overwrite_success_count > 0 || overwrite_fail_count > 0 || already_done_count > 0 || skipped_message_count > 0 || skipped_archived_count > 0 || protected_count > 0 {
         println!("\n--- Overwrite Summary ---");
         println!("Successfully overwrote: {} items", overwrite_success_count);
         println!("Failed to overwrite:    {} items", overwrite_fail_count);
+        println!("Retried requests:       {}", api.retries() - retries_before);
+        if already_done_count > 0 {
+            println!("Already done (journal): {} items", already_done_count);
+        }
+        if skipped_message_count > 0 {
+            println!("Skipped (messages):     {} items", skipped_message_count);
+        }
+        if skipped_archived_count > 0 {
+            println!("Skipped (archived):     {} items", skipped_archived_count);
+        }
+        if !ctx.protect.is_empty() {
+            println!("Protected (skipped):    {} items", protected_count);
+        }
     }
-    Ok(())
+    Ok(overwrite_success_count)
 }
 
+/// Delete every unprotected item. Returns the ids of the items deleted by this call.
 pub async fn handle_delete_action(
-    reddit: &Me,
+    api: &RedditApi,
     items_to_delete: &[UnifiedItem],
-    skip_confirmation: bool,
-    debug_mode: bool,
-) -> Result<usize, Box<dyn Error>> {
+    ctx: &mut ActionContext,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let dry_run = ctx.dry_run;
+    let debug_mode = ctx.debug_mode;
+    let (items_to_delete, protected) = split_protected(items_to_delete, &ctx.protect);
     if items_to_delete.is_empty() {
-        if debug_mode {
+        if !protected.is_empty() {
+            println!("\nAll {} items are protected; nothing to delete.", protected.len());
+        } else if debug_mode {
             println!("No items found to delete based on current filters.");
         }
-        return Ok(0);
+        return Ok(Vec::new());
     }
 
     let num_items_to_delete = items_to_delete.len();
-    println!("\nPreparing to delete {} items.", num_items_to_delete);
-
-    let mut confirmed_to_delete = skip_confirmation;
-
-    if !confirmed_to_delete {
-        print!("Are you sure you want to delete these {} items? (yes/No): ", num_items_to_delete);
-        io::stdout().flush().map_err(|e| Box::new(e) as Box<dyn Error>)?;
-        let mut confirmation_input = String::new();
-        io::stdin().read_line(&mut confirmation_input).map_err(|e| Box::new(e) as Box<dyn Error>)?;
-        if confirmation_input.trim().to_lowercase() == "yes" {
-            confirmed_to_delete = true;
+    if dry_run {
+        println!("\n--- Dry run: delete (no changes will be made) ---");
+        print_protected(&protected);
+        for item in &items_to_delete {
+            print_dry_run_item("delete", item);
+        }
+        println!("\n--- Deletion Summary (dry run) ---");
+        println!("  Items targeted for deletion: {}", num_items_to_delete);
+        println!("  Would delete:                {}", num_items_to_delete);
+        if !ctx.protect.is_empty() {
+            println!("  Protected (skipped):         {}", protected.len());
         }
+        return Ok(Vec::new());
+    }
+
+    if debug_mode {
+        print_protected(&protected);
     }
+    println!("\nPreparing to delete {} items.", num_items_to_delete);
+
+    let confirmed_to_delete = ctx.skip_confirmation || confirm("delete", num_items_to_delete)?;
 
     if confirmed_to_delete {
         println!("Proceeding with deletion...");
-        let mut deleted_count = 0;
+        let mut deleted_ids = Vec::new();
         let mut failed_count = 0;
+        let retries_before = api.retries();
+        let already_done_count = record_pending(ctx.journal.as_mut(), JournalAction::Delete, &items_to_delete)?;
 
         for (index, item) in items_to_delete.iter().enumerate() {
+            if ctx.journal.as_ref().is_some_and(|j| j.is_done(JournalAction::Delete, &item.id)) {
+                if debug_mode {
+                    println!("Skipping item {}/{} (ID: {}), already deleted according to journal", index + 1, num_items_to_delete, item.id);
+                }
+                continue;
+            }
             if debug_mode {
                 println!("Deleting item {}/{} (ID: {})...", index + 1, num_items_to_delete, item.id);
             }
-            let delete_url = roux::util::url::build_oauth("api/del");
+            if let Some(backup) = ctx.backup.as_mut() {
+                backup.append(item, JournalAction::Delete)?;
+            }
+            // Private messages have their own delete endpoint
+            let endpoint = if item.item_type == "Message" { "api/del_msg" } else { "api/del" };
             let params = [("id", item.id.as_str())];
-            let token = reddit.config.access_token.as_deref().ok_or_else(|| {
-                Box::new(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    "Access token is None after successful login, cannot proceed with deletion."
-                )) as Box<dyn Error>
-            })?;
-            match reddit.client
-                .post(&delete_url)
-                .bearer_auth(token)
-                .form(&params)
-                .send()
-                .await
-                .map_err(RouxError::from)
-            {
+            match api.post_form(endpoint, &params).await {
                 Ok(response) => {
                     if response.status().is_success() {
                         let _ = response.text().await; 
                         if debug_mode {
                             println!("Successfully deleted item: {}", item.id);
                         }
-                        deleted_count += 1;
+                        record_result(ctx.journal.as_mut(), JournalAction::Delete, &item.id, None)?;
+                        if let Some(archive) = ctx.archive.as_mut() {
+                            archive.mark(&item.id, JournalAction::Delete)?;
+                        }
+                        deleted_ids.push(item.id.clone());
                     } else {
                         let status = response.status();
                         let error_body = response.text().await.unwrap_or_else(|e| format!("Could not read error response body: {}", e));
@@ -120,29 +279,260 @@ pub async fn handle_delete_action(
                             status,
                             error_body
                         );
+                        record_result(ctx.journal.as_mut(), JournalAction::Delete, &item.id, Some(status.to_string()))?;
                         failed_count += 1;
                     }
                 }
                 Err(e) => {
                     eprintln!("Error sending delete request for item {}: {}", item.id, e);
                     if debug_mode {
-                        eprintln!("Debug details for reqwest error: {:?}", e);
+                        eprintln!("Debug details for request error: {:?}", e);
                     }
+                    record_result(ctx.journal.as_mut(), JournalAction::Delete, &item.id, Some(e.to_string()))?;
                     failed_count += 1;
                 }
             }
         }
         println!("\n--- Deletion Summary ---");
         println!("  Items targeted for deletion: {}", num_items_to_delete);
-        println!("  Successfully deleted:        {}", deleted_count);
+        println!("  Successfully deleted:        {}", deleted_ids.len());
         println!("  Failed to delete:            {}", failed_count);
-        Ok(deleted_count)
+        println!("  Retried requests:            {}", api.retries() - retries_before);
+        if already_done_count > 0 {
+            println!("  Already done (journal):      {}", already_done_count);
+        }
+        if !ctx.protect.is_empty() {
+            println!("  Protected (skipped):         {}", protected.len());
+        }
+        Ok(deleted_ids)
     } else {
         println!("Deletion aborted by user.");
-        Ok(0)
+        Ok(Vec::new())
+    }
+}
+
+/// A reversible bulk action on items of the saved, upvoted/downvoted or hidden listings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum BulkAction {
+    Unsave,
+    Unvote,
+    Unhide,
+}
+
+impl BulkAction {
+    fn verb(&self) -> &'static str {
+        match self {
+            BulkAction::Unsave => "unsave",
+            BulkAction::Unvote => "unvote",
+            BulkAction::Unhide => "unhide",
+        }
+    }
+
+    fn past_tense(&self) -> &'static str {
+        match self {
+            BulkAction::Unsave => "unsaved",
+            BulkAction::Unvote => "unvoted",
+            BulkAction::Unhide => "unhidden",
+        }
+    }
+
+    fn endpoint(&self) -> &'static str {
+        match self {
+            BulkAction::Unsave => "api/unsave",
+            BulkAction::Unvote => "api/vote",
+            BulkAction::Unhide => "api/unhide",
+        }
     }
 }
 
+/// Apply an unsave, unvote (`dir=0`) or unhide to every item, with the same confirmation
+/// prompt and summary as deletion. Returns the number of items changed.
+pub async fn handle_bulk_action(
+    api: &RedditApi,
+    items: &[UnifiedItem],
+    action: BulkAction,
+    ctx: &ActionContext,
+) -> Result<usize, Box<dyn Error>> {
+    let verb = action.verb();
+    let (items, protected) = split_protected(items, &ctx.protect);
+    if items.is_empty() {
+        if !protected.is_empty() {
+            println!("\nAll {} items are protected; nothing to {}.", protected.len(), verb);
+        } else if ctx.debug_mode {
+            println!("No items found to {} based on current filters.", verb);
+        }
+        return Ok(0);
+    }
+
+    let num_items = items.len();
+    if ctx.dry_run {
+        println!("\n--- Dry run: {} (no changes will be made) ---", verb);
+        print_protected(&protected);
+        for item in &items {
+            print_dry_run_item(verb, item);
+        }
+        println!("\n--- {} Summary (dry run) ---", capitalize(verb));
+        println!("  {:<24}{}", "Items targeted:", num_items);
+        println!("  {:<24}{}", format!("Would {}:", verb), num_items);
+        if !ctx.protect.is_empty() {
+            println!("  {:<24}{}", "Protected (skipped):", protected.len());
+        }
+        return Ok(0);
+    }
+
+    if ctx.debug_mode {
+        print_protected(&protected);
+    }
+    println!("\nPreparing to {} {} items.", verb, num_items);
+    if !(ctx.skip_confirmation || confirm(verb, num_items)?) {
+        println!("{} aborted by user.", capitalize(verb));
+        return Ok(0);
+    }
+
+    let mut success_count = 0;
+    let mut failed_count = 0;
+    let retries_before = api.retries();
+    for (index, item) in items.iter().enumerate() {
+        if ctx.debug_mode {
+            println!("Sending {} for item {}/{} (ID: {})...", verb, index + 1, num_items, item.id);
+        }
+        let mut params = vec![("id", item.id.as_str())];
+        if action == BulkAction::Unvote {
+            params.push(("dir", "0"));
+        }
+        match api.post_form(action.endpoint(), &params).await {
+            Ok(response) if response.status().is_success() => {
+                let _ = response.text().await;
+                success_count += 1;
+            }
+            Ok(response) => {
+                let status = response.status();
+                let error_body = response.text().await.unwrap_or_else(|e| format!("Could not read error response body: {}", e));
+                eprintln!("Failed to {} item {} - API Error Status: {}. Details: {}", verb, item.id, status, error_body);
+                failed_count += 1;
+            }
+            Err(e) => {
+                eprintln!("Error sending {} request for item {}: {}", verb, item.id, e);
+                failed_count += 1;
+            }
+        }
+    }
+
+    println!("\n--- {} Summary ---", capitalize(verb));
+    println!("  {:<24}{}", "Items targeted:", num_items);
+    println!("  {:<24}{}", format!("Successfully {}:", action.past_tense()), success_count);
+    println!("  {:<24}{}", format!("Failed to {}:", verb), failed_count);
+    println!("  {:<24}{}", "Retried requests:", api.retries() - retries_before);
+    if !ctx.protect.is_empty() {
+        println!("  {:<24}{}", "Protected (skipped):", protected.len());
+    }
+    Ok(success_count)
+}
+
+/// Why the overwrite action leaves an item alone.
+#[derive(Debug, PartialEq)]
+enum OverwriteSkip {
+    Protected(String),
+    /// Private messages cannot be edited.
+    Message,
+    /// Archived posts and comments cannot be edited.
+    Archived,
+}
+
+fn overwrite_skip(item: &UnifiedItem, protect: &ProtectList) -> Option<OverwriteSkip> {
+    if let Some(reason) = protect.reason(item) {
+        return Some(OverwriteSkip::Protected(reason));
+    }
+    if item.item_type == "Message" {
+        Some(OverwriteSkip::Message)
+    } else if item.flags.archived {
+        Some(OverwriteSkip::Archived)
+    } else {
+        None
+    }
+}
+
+/// Separate the items `protect` keeps from those an action may change. Protected items are
+/// returned as "comment t1_abc (reason)" descriptions.
+fn split_protected(items: &[UnifiedItem], protect: &ProtectList) -> (Vec<UnifiedItem>, Vec<String>) {
+    let mut allowed = Vec::with_capacity(items.len());
+    let mut protected = Vec::new();
+    for item in items {
+        match protect.reason(item) {
+            Some(reason) => protected.push(format!("{} {} ({})", item.item_type.to_lowercase(), item.id, reason)),
+            None => allowed.push(item.clone()),
+        }
+    }
+    (allowed, protected)
+}
+
+fn print_protected(protected: &[String]) {
+    for description in protected {
+        println!("Protected, skipping {}", description);
+    }
+}
+
+/// Ask the user to type "yes" before `verb` is applied to `count` items.
+fn confirm(verb: &str, count: usize) -> Result<bool, Box<dyn Error>> {
+    print!("Are you sure you want to {} these {} items? (yes/No): ", verb, count);
+    io::stdout().flush().map_err(|e| Box::new(e) as Box<dyn Error>)?;
+    let mut confirmation_input = String::new();
+    io::stdin().read_line(&mut confirmation_input).map_err(|e| Box::new(e) as Box<dyn Error>)?;
+    Ok(confirmation_input.trim().to_lowercase() == "yes")
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Mark every item not yet completed as pending in the journal, so an interrupted run
+/// leaves a record of the remaining work. Returns how many items were already done.
+fn record_pending(
+    journal: Option<&mut Journal>,
+    action: JournalAction,
+    items: &[UnifiedItem],
+) -> Result<usize, Box<dyn Error>> {
+    let Some(journal) = journal else {
+        return Ok(0);
+    };
+    let mut already_done_count = 0;
+    for item in items {
+        if journal.is_done(action, &item.id) {
+            already_done_count += 1;
+        } else {
+            journal.record(&item.id, action, JournalResult::Pending, None)?;
+        }
+    }
+    Ok(already_done_count)
+}
+
+fn record_result(
+    journal: Option<&mut Journal>,
+    action: JournalAction,
+    id: &str,
+    error: Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    if let Some(journal) = journal {
+        let result = if error.is_some() { JournalResult::Failed } else { JournalResult::Done };
+        journal.record(id, action, result, error)?;
+    }
+    Ok(())
+}
+
+fn print_dry_run_item(action: &str, item: &UnifiedItem) {
+    println!("Would {} {} {}", action, item.item_type.to_lowercase(), item.id);
+    println!("    Permalink:   https://reddit.com{}", item.permalink);
+}
+
+/// Render content on a single line so multi-line bodies stay readable in the preview.
+fn preview_text(text: &str) -> String {
+    text.replace("\r\n", "\\n").replace(['\n', '\r'], "\\n")
+}
+
 pub fn handle_csv_export(
     items: &[UnifiedItem],
     file_path: &str,
@@ -162,6 +552,9 @@ pub fn handle_csv_export(
         "NumComments",
         "Permalink",
         "TimestampUTC",
+        "Source",
+        "Author",
+        "Recipient",
     ])?;
 
     for item in items {
@@ -175,6 +568,9 @@ pub fn handle_csv_export(
             &item.num_comments.to_string(),
             &format!("https://reddit.com{}", item.permalink),
             &item.created_utc.to_string(),
+            &item.source,
+            &item.author,
+            &item.recipient,
         ])?;
     }
 
@@ -185,6 +581,55 @@ pub fn handle_csv_export(
     Ok(())
 }
 
+/// JSON representation of an item: every `UnifiedItem` field plus an ISO-8601 timestamp.
+#[derive(Serialize)]
+struct ExportRecord<'a> {
+    #[serde(flatten)]
+    item: &'a UnifiedItem,
+    created_iso: String,
+}
+
+/// Write items as a JSON array or as newline-delimited JSON, to `file_path` or stdout.
+pub fn handle_json_export(
+    items: &[UnifiedItem],
+    format: OutputFormat,
+    file_path: Option<&str>,
+    debug_mode: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut writer: Box<dyn Write> = match file_path {
+        Some(path) => {
+            if debug_mode {
+                println!("\nExporting {} items as {:?} to file: {}", items.len(), format, path);
+            }
+            Box::new(BufWriter::new(File::create(path)?))
+        }
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+
+    let records = items.iter().map(|item| ExportRecord {
+        item,
+        created_iso: format_timestamp_iso(item.created_utc),
+    });
+
+    if format == OutputFormat::Ndjson {
+        for record in records {
+            serde_json::to_writer(&mut writer, &record)?;
+            writeln!(writer)?;
+        }
+    } else {
+        serde_json::to_writer_pretty(&mut writer, &records.collect::<Vec<_>>())?;
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+
+    if debug_mode {
+        if let Some(path) = file_path {
+            println!("Successfully exported {} items to {}", items.len(), path);
+        }
+    }
+    Ok(())
+}
+
 pub fn handle_print_to_console(items: &[UnifiedItem], debug_mode: bool) {
     if items.is_empty() {
         if debug_mode {
@@ -193,14 +638,14 @@ pub fn handle_print_to_console(items: &[UnifiedItem], debug_mode: bool) {
         return;
     }
 
-    println!("Type,Subreddit,Title,Content,Upvotes,NumComments,Permalink,TimestampUTC");
+    println!("Type,Subreddit,Title,Content,Upvotes,NumComments,Permalink,TimestampUTC,Source,Author,Recipient");
     for item in items {
         let escaped_title = escape_csv_field(&item.title);
         let escaped_content = escape_csv_field(&item.content);
         let subreddit_prefix = if item.subreddit.is_empty() { "" } else { "r/" };
 
         println!(
-            "\"{}\",\"{}{}\",\"{}\",\"{}\",{},{},\"https://reddit.com{}\",{}",
+            "\"{}\",\"{}{}\",\"{}\",\"{}\",{},{},\"https://reddit.com{}\",{},\"{}\",\"{}\",\"{}\"",
             item.item_type,
             subreddit_prefix,
             item.subreddit,
@@ -209,7 +654,120 @@ pub fn handle_print_to_console(items: &[UnifiedItem], debug_mode: bool) {
             item.upvotes,
             item.num_comments,
             item.permalink,
-            item.created_utc
+            item.created_utc,
+            item.source,
+            item.author,
+            item.recipient
+        );
+    }
+}
+
+/// Print backups (most recent first), optionally only those matching `search_text`.
+/// Listing shows a one-line preview per item; searching prints the full original content.
+pub fn handle_backups_output(
+    backup_path: &Path,
+    search_text: Option<&str>,
+    limit: Option<usize>,
+    debug_mode: bool,
+) -> Result<(), Box<dyn Error>> {
+    if debug_mode {
+        println!("Reading backups from {}", backup_path.display());
+    }
+    let records = read_backups(backup_path)?;
+    let matching: Vec<_> = records
+        .iter()
+        .rev()
+        .filter(|record| search_text.is_none_or(|text| record.matches(text)))
+        .take(limit.unwrap_or(usize::MAX))
+        .collect();
+
+    if matching.is_empty() {
+        println!("No backups found.");
+        return Ok(());
+    }
+
+    for record in matching {
+        let item = &record.item;
+        let subreddit_prefix = if item.subreddit.is_empty() { "" } else { "r/" };
+        println!(
+            "{}  {:<9}  {} {}  {}{}  https://reddit.com{}",
+            record.backed_up_at,
+            format!("{:?}", record.action).to_lowercase(),
+            item.item_type.to_lowercase(),
+            item.id,
+            subreddit_prefix,
+            item.subreddit,
+            item.permalink
         );
+        if search_text.is_some() {
+            if !item.title.is_empty() {
+                println!("    Title:   {}", item.title);
+            }
+            println!("    Score:   {}", item.upvotes);
+            println!("    Content: {}", preview_text(&item.content));
+        } else {
+            let preview: String = preview_text(&item.content).chars().take(80).collect();
+            println!("    {}", preview);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn bulk_actions_use_their_endpoints() {
+        assert_eq!(BulkAction::Unsave.endpoint(), "api/unsave");
+        assert_eq!(BulkAction::Unvote.endpoint(), "api/vote");
+        assert_eq!(BulkAction::Unhide.past_tense(), "unhidden");
+        assert_eq!(capitalize(BulkAction::Unvote.verb()), "Unvote");
+    }
+
+    #[test]
+    fn ndjson_export_has_stable_fields_and_iso_timestamp() -> Result<(), Box<dyn Error>> {
+        let temp = tempdir()?;
+        let path = temp.path().join("items.ndjson");
+        let mut item = UnifiedItem::from_fullname("t3_abc");
+        item.created_utc = 1705314600.0;
+
+        handle_json_export(&[item.clone(), item], OutputFormat::Ndjson, path.to_str(), false)?;
+
+        let contents = std::fs::read_to_string(&path)?;
+        let lines: Vec<_> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let record: serde_json::Value = serde_json::from_str(lines[0])?;
+        assert_eq!(record["id"], "t3_abc");
+        assert_eq!(record["item_type"], "Post");
+        assert_eq!(record["created_utc"], 1705314600.0);
+        assert_eq!(record["created_iso"], "2024-01-15T10:30:00Z");
+        Ok(())
+    }
+
+    #[test]
+    fn json_export_writes_an_array() -> Result<(), Box<dyn Error>> {
+        let temp = tempdir()?;
+        let path = temp.path().join("items.json");
+        handle_json_export(&[UnifiedItem::from_fullname("t1_abc")], OutputFormat::Json, path.to_str(), false)?;
+
+        let records: Vec<serde_json::Value> = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["item_type"], "Comment");
+        Ok(())
+    }
+
+    #[test]
+    fn overwrite_skips_protected_messages_and_archived_items() {
+        let mut protect = ProtectList::default();
+        protect.gilded = true;
+        let mut archived = UnifiedItem::from_fullname("t1_abc");
+        archived.flags.archived = true;
+        assert_eq!(overwrite_skip(&archived, &protect), Some(OverwriteSkip::Archived));
+        archived.gilded = 1;
+        assert_eq!(overwrite_skip(&archived, &protect), Some(OverwriteSkip::Protected("gilded".to_string())));
+        assert_eq!(overwrite_skip(&UnifiedItem::from_fullname("t4_abc"), &protect), Some(OverwriteSkip::Message));
+        assert_eq!(overwrite_skip(&UnifiedItem::from_fullname("t3_abc"), &protect), None);
     }
 }
@@ -0,0 +1,419 @@
+// Shared request layer for every call Bydit makes to Reddit's OAuth API.
+//
+// Reddit reports the remaining request budget of the current window in the
+// `X-Ratelimit-Remaining` / `X-Ratelimit-Reset` response headers. Every request goes through
+// `RedditApi::send`, which records those headers and sleeps until the window resets before
+// the budget runs out, instead of firing requests back-to-back into 429s. Transient failures
+// (connection errors and the statuses listed in `RetryPolicy`) are retried with backoff.
+// Access tokens are renewed shortly before they expire, and once more if Reddit answers 401.
+use crate::auth::{Authenticator, TokenResponse};
+use reqwest::header::{HeaderMap, HeaderValue, RETRY_AFTER, USER_AGENT};
+use reqwest::{Client, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::error::Error;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const OAUTH_BASE_URL: &str = "https://oauth.reddit.com/";
+
+/// Requests kept in reserve: once the remaining budget drops to this, wait for the reset.
+const RATE_LIMIT_RESERVE: f64 = 2.0;
+
+/// Extra time added after the advertised reset, to absorb clock skew and rounding.
+const RESET_GRACE: Duration = Duration::from_secs(1);
+
+/// Longest wait taken from a reset or `Retry-After` header, so a bogus value cannot stall a run.
+const MAX_HEADER_WAIT: Duration = Duration::from_secs(3600);
+
+/// Renew the access token when it expires within this margin.
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(300);
+
+/// How failed requests are retried. Configured through the optional `[retry]` table in
+/// `config.toml`; every field falls back to its default when omitted.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Total attempts per request, including the first one. `1` disables retries.
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled for every further attempt.
+    pub backoff_base_ms: u64,
+    /// Upper bound of the random delay added to each backoff, so retries do not line up.
+    pub jitter_ms: u64,
+    /// HTTP statuses treated as transient. Connection errors are always retried.
+    pub retryable_statuses: Vec<u16>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 4,
+            backoff_base_ms: 1000,
+            jitter_ms: 500,
+            retryable_statuses: vec![429, 500, 502, 503, 504],
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn is_retryable(&self, status: StatusCode) -> bool {
+        self.retryable_statuses.contains(&status.as_u16())
+    }
+
+    /// Backoff before retry number `retry` (1-based), without jitter.
+    fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u64.saturating_pow(retry.saturating_sub(1));
+        Duration::from_millis(self.backoff_base_ms.saturating_mul(factor))
+    }
+
+    fn delay(&self, retry: u32) -> Duration {
+        let jitter = if self.jitter_ms > 0 { fastrand::u64(0..=self.jitter_ms) } else { 0 };
+        self.backoff(retry) + Duration::from_millis(jitter)
+    }
+}
+
+/// Request budget as last reported by Reddit.
+#[derive(Debug, Default)]
+struct RateLimitState {
+    remaining: Option<f64>,
+    reset_at: Option<Instant>,
+}
+
+impl RateLimitState {
+    fn update_from_headers(&mut self, headers: &HeaderMap, now: Instant) {
+        let remaining = header_f64(headers, "x-ratelimit-remaining");
+        let reset = header_duration(headers, "x-ratelimit-reset");
+        if let (Some(remaining), Some(reset)) = (remaining, reset) {
+            self.remaining = Some(remaining);
+            self.reset_at = Some(now + reset);
+        }
+    }
+
+    /// How long to wait before the next request may be sent.
+    fn delay_before_next_request(&self, now: Instant) -> Option<Duration> {
+        match (self.remaining, self.reset_at) {
+            (Some(remaining), Some(reset_at)) if remaining <= RATE_LIMIT_RESERVE && reset_at > now => {
+                Some(reset_at - now + RESET_GRACE)
+            }
+            _ => None,
+        }
+    }
+
+    /// After waiting out the window the old numbers no longer apply.
+    fn clear(&mut self) {
+        self.remaining = None;
+        self.reset_at = None;
+    }
+}
+
+fn header_f64(headers: &HeaderMap, name: &str) -> Option<f64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+/// A header holding seconds, clamped to `0..=MAX_HEADER_WAIT`. Non-finite values are ignored.
+fn header_duration(headers: &HeaderMap, name: &str) -> Option<Duration> {
+    let secs = header_f64(headers, name).filter(|secs| secs.is_finite())?;
+    Some(Duration::from_secs_f64(secs.clamp(0.0, MAX_HEADER_WAIT.as_secs_f64())))
+}
+
+/// The access token in use and when Reddit said it expires (unknown if Reddit did not say).
+struct AccessToken {
+    value: String,
+    expires_at: Option<Instant>,
+}
+
+impl AccessToken {
+    fn from_response(token: TokenResponse, now: Instant) -> AccessToken {
+        let expires_at = (token.expires_in > 0).then(|| now + Duration::from_secs(token.expires_in));
+        AccessToken { value: token.access_token, expires_at }
+    }
+
+    fn needs_refresh(&self, now: Instant) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= now + TOKEN_REFRESH_MARGIN)
+    }
+}
+
+/// Authenticated, rate-limit aware access to Reddit's OAuth endpoints.
+pub struct RedditApi {
+    client: Client,
+    authenticator: Authenticator,
+    access_token: Mutex<AccessToken>,
+    username: String,
+    rate_limit: Mutex<RateLimitState>,
+    retry_policy: RetryPolicy,
+    retry_count: AtomicUsize,
+    debug_mode: bool,
+}
+
+impl RedditApi {
+    pub fn new(
+        user_agent: &str,
+        username: &str,
+        authenticator: Authenticator,
+        token: TokenResponse,
+        retry_policy: RetryPolicy,
+        debug_mode: bool,
+    ) -> Result<RedditApi, Box<dyn Error>> {
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_str(user_agent)?);
+        let client = Client::builder().default_headers(headers).build()?;
+
+        Ok(RedditApi {
+            client,
+            authenticator,
+            access_token: Mutex::new(AccessToken::from_response(token, Instant::now())),
+            username: username.to_string(),
+            rate_limit: Mutex::new(RateLimitState::default()),
+            retry_policy,
+            retry_count: AtomicUsize::new(0),
+            debug_mode,
+        })
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn set_username(&mut self, username: &str) {
+        self.username = username.to_string();
+    }
+
+    /// Number of requests retried so far in this run.
+    pub fn retries(&self) -> usize {
+        self.retry_count.load(Ordering::Relaxed)
+    }
+
+    /// GET an OAuth path such as `user/<name>/comments/.json?limit=100`.
+    pub async fn get(&self, path: &str) -> Result<Response, Box<dyn Error>> {
+        let url = format!("{}{}", OAUTH_BASE_URL, path);
+        if self.debug_mode { println!("GET {}", url); }
+        self.send(self.client.get(&url)).await
+    }
+
+    /// GET an OAuth path and parse the JSON body, failing on non-success statuses.
+    pub async fn get_json<T: DeserializeOwned>(&self, path: &str) -> Result<T, Box<dyn Error>> {
+        let response = self.get(path).await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(format!("GET {} failed with status {}", path, status).into());
+        }
+        // Decode here rather than with `Response::json`, whose error hides serde's message
+        // (e.g. an unknown `kind` in a listing)
+        let body = response.text().await?;
+        serde_json::from_str(&body).map_err(|e| format!("Unexpected response from GET {}: {}", path, e).into())
+    }
+
+    /// POST a form to an OAuth path such as `api/del`.
+    pub async fn post_form(&self, path: &str, form: &[(&str, &str)]) -> Result<Response, Box<dyn Error>> {
+        let url = format!("{}{}", OAUTH_BASE_URL, path);
+        if self.debug_mode { println!("POST {}", url); }
+        self.send(self.client.post(&url).form(form)).await
+    }
+
+    async fn send(&self, request: RequestBuilder) -> Result<Response, Box<dyn Error>> {
+        let max_attempts = self.retry_policy.max_attempts.max(1);
+        let mut attempt = 1;
+        let mut reauthenticated = false;
+
+        loop {
+            self.wait_for_rate_limit().await;
+            let access_token = self.current_access_token().await?;
+
+            // GET and form requests have in-memory bodies, so they can always be cloned.
+            let this_attempt = request.try_clone().ok_or("Request cannot be cloned for retrying")?;
+            let can_retry = attempt < max_attempts;
+
+            let retry_after = match this_attempt.bearer_auth(&access_token).send().await {
+                Ok(response) => {
+                    if self.debug_mode { println!("Status: {}", response.status()); }
+                    self.record_rate_limit(response.headers());
+
+                    let status = response.status();
+                    if status == StatusCode::UNAUTHORIZED && !reauthenticated {
+                        // The token may have been revoked or expired early; renew it and try once more
+                        eprintln!("Access token rejected (401), logging in again and retrying...");
+                        self.refresh_access_token().await?;
+                        reauthenticated = true;
+                        continue;
+                    }
+                    if !can_retry || !self.retry_policy.is_retryable(status) {
+                        return Ok(response);
+                    }
+                    eprintln!("Request failed with status {} (attempt {}/{}), retrying...", status, attempt, max_attempts);
+                    header_duration(response.headers(), RETRY_AFTER.as_str())
+                }
+                Err(e) => {
+                    if !can_retry {
+                        return Err(Box::new(e));
+                    }
+                    eprintln!("Request error: {} (attempt {}/{}), retrying...", e, attempt, max_attempts);
+                    None
+                }
+            };
+
+            let delay = self.retry_policy.delay(attempt).max(retry_after.unwrap_or_default());
+            if self.debug_mode {
+                println!("Waiting {:.1}s before retrying.", delay.as_secs_f64());
+            }
+            tokio::time::sleep(delay).await;
+            self.retry_count.fetch_add(1, Ordering::Relaxed);
+            attempt += 1;
+        }
+    }
+
+    /// The access token to send, renewed first if it is about to expire.
+    async fn current_access_token(&self) -> Result<String, Box<dyn Error>> {
+        let needs_refresh = self
+            .access_token
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .needs_refresh(Instant::now());
+        if needs_refresh {
+            if self.debug_mode { println!("Access token expires soon, renewing it."); }
+            self.refresh_access_token().await?;
+        }
+        Ok(self.access_token.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).value.clone())
+    }
+
+    async fn refresh_access_token(&self) -> Result<(), Box<dyn Error>> {
+        let token = self.authenticator.fetch_token().await.map_err(|e| {
+            format!("Failed to renew the Reddit access token: {}", e)
+        })?;
+        if self.debug_mode { println!("Renewed access token (valid for {}s).", token.expires_in); }
+        *self.access_token.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) =
+            AccessToken::from_response(token, Instant::now());
+        Ok(())
+    }
+
+    fn record_rate_limit(&self, headers: &HeaderMap) {
+        let mut state = self.rate_limit.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.update_from_headers(headers, Instant::now());
+        if self.debug_mode {
+            if let Some(remaining) = state.remaining {
+                println!("Rate limit: {} requests remaining in this window.", remaining);
+            }
+        }
+    }
+
+    async fn wait_for_rate_limit(&self) {
+        let delay = {
+            let state = self.rate_limit.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            state.delay_before_next_request(Instant::now())
+        };
+        if let Some(delay) = delay {
+            eprintln!(
+                "Reddit rate limit nearly exhausted, waiting {}s for the window to reset...",
+                delay.as_secs()
+            );
+            tokio::time::sleep(delay).await;
+            self.rate_limit.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(remaining: &str, reset: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-remaining", HeaderValue::from_str(remaining).unwrap());
+        headers.insert("x-ratelimit-reset", HeaderValue::from_str(reset).unwrap());
+        headers
+    }
+
+    #[test]
+    fn no_delay_while_budget_remains() {
+        let now = Instant::now();
+        let mut state = RateLimitState::default();
+        state.update_from_headers(&headers("57.0", "300"), now);
+        assert_eq!(state.delay_before_next_request(now), None);
+    }
+
+    #[test]
+    fn waits_for_reset_when_budget_is_nearly_exhausted() {
+        let now = Instant::now();
+        let mut state = RateLimitState::default();
+        state.update_from_headers(&headers("1.0", "42"), now);
+        assert_eq!(
+            state.delay_before_next_request(now),
+            Some(Duration::from_secs(42) + RESET_GRACE)
+        );
+    }
+
+    #[test]
+    fn retry_backoff_doubles_per_attempt() {
+        let policy = RetryPolicy { jitter_ms: 0, ..RetryPolicy::default() };
+        assert_eq!(policy.backoff(1), Duration::from_millis(1000));
+        assert_eq!(policy.backoff(2), Duration::from_millis(2000));
+        assert_eq!(policy.backoff(3), Duration::from_millis(4000));
+        assert_eq!(policy.delay(2), Duration::from_millis(2000));
+    }
+
+    #[test]
+    fn jitter_stays_within_bound() {
+        let policy = RetryPolicy { backoff_base_ms: 100, jitter_ms: 50, ..RetryPolicy::default() };
+        for _ in 0..100 {
+            let delay = policy.delay(1);
+            assert!(delay >= Duration::from_millis(100) && delay <= Duration::from_millis(150));
+        }
+    }
+
+    #[test]
+    fn only_listed_statuses_are_retryable() {
+        let policy = RetryPolicy::default();
+        assert!(policy.is_retryable(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(policy.is_retryable(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!policy.is_retryable(StatusCode::FORBIDDEN));
+    }
+
+    fn token_response(expires_in: u64) -> TokenResponse {
+        TokenResponse { access_token: "token".to_string(), refresh_token: None, expires_in }
+    }
+
+    #[test]
+    fn token_is_renewed_shortly_before_expiry() {
+        let now = Instant::now();
+        let token = AccessToken::from_response(token_response(3600), now);
+        assert!(!token.needs_refresh(now));
+        assert!(!token.needs_refresh(now + Duration::from_secs(3000)));
+        assert!(token.needs_refresh(now + Duration::from_secs(3400)));
+        assert!(!AccessToken::from_response(token_response(0), now).needs_refresh(now + Duration::from_secs(86400)));
+    }
+
+    #[test]
+    fn ignores_missing_or_malformed_headers() {
+        let now = Instant::now();
+        let mut state = RateLimitState::default();
+        state.update_from_headers(&headers("not-a-number", "42"), now);
+        assert_eq!(state.remaining, None);
+        state.update_from_headers(&HeaderMap::new(), now);
+        assert_eq!(state.delay_before_next_request(now), None);
+    }
+
+    #[test]
+    fn out_of_range_reset_values_do_not_panic() {
+        let now = Instant::now();
+        let mut state = RateLimitState::default();
+        state.update_from_headers(&headers("0", "inf"), now);
+        assert_eq!(state.remaining, None);
+        state.update_from_headers(&headers("0", "-1"), now);
+        assert_eq!(state.reset_at, Some(now));
+        state.update_from_headers(&headers("0", "1e12"), now);
+        assert_eq!(state.reset_at, Some(now + MAX_HEADER_WAIT));
+    }
+
+    #[test]
+    fn retry_after_is_clamped() {
+        let retry_after = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(RETRY_AFTER, HeaderValue::from_str(value).unwrap());
+            header_duration(&headers, RETRY_AFTER.as_str())
+        };
+        assert_eq!(retry_after("2.5"), Some(Duration::from_millis(2500)));
+        assert_eq!(retry_after("-1"), Some(Duration::ZERO));
+        assert_eq!(retry_after("NaN"), None);
+        assert_eq!(retry_after("inf"), None);
+    }
+}
@@ -0,0 +1,178 @@
+// Local SQLite archive of every item Bydit has fetched (`--sqlite <path>`).
+//
+// Items are upserted by fullname on every run. `first_seen`/`last_seen` track when an item was
+// fetched, `score_history` keeps one row per observed score change, and rows are stamped with
+// `overwritten_at`/`deleted_at` when the overwrite or delete actions change them on Reddit.
+// The original content of an overwritten item is kept rather than replaced by the new text.
+use crate::journal::JournalAction;
+use crate::models::UnifiedItem;
+use chrono::{SecondsFormat, Utc};
+use rusqlite::{params, Connection};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS items (
+    id             TEXT PRIMARY KEY,
+    item_type      TEXT NOT NULL,
+    subreddit      TEXT NOT NULL,
+    title          TEXT NOT NULL,
+    content        TEXT NOT NULL,
+    upvotes        INTEGER NOT NULL,
+    num_comments   INTEGER NOT NULL,
+    permalink      TEXT NOT NULL,
+    created_utc    REAL NOT NULL,
+    source         TEXT NOT NULL,
+    author         TEXT NOT NULL,
+    recipient      TEXT NOT NULL,
+    first_seen     TEXT NOT NULL,
+    last_seen      TEXT NOT NULL,
+    overwritten_at TEXT,
+    deleted_at     TEXT
+);
+CREATE TABLE IF NOT EXISTS score_history (
+    id          TEXT NOT NULL REFERENCES items(id),
+    recorded_at TEXT NOT NULL,
+    upvotes     INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS score_history_id ON score_history(id);
+";
+
+pub struct SqliteArchive {
+    path: PathBuf,
+    conn: Connection,
+}
+
+impl SqliteArchive {
+    /// Open (or create) the archive database at `path` and make sure the schema exists.
+    pub fn open(path: &Path) -> Result<SqliteArchive, Box<dyn Error>> {
+        let conn = Connection::open(path)
+            .map_err(|e| format!("Failed to open SQLite archive '{}': {}", path.display(), e))?;
+        conn.execute_batch(SCHEMA)?;
+        Ok(SqliteArchive { path: path.to_path_buf(), conn })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Insert new items and refresh known ones. A score row is added for new items and
+    /// whenever the score differs from the last one recorded.
+    pub fn upsert_items(&mut self, items: &[UnifiedItem]) -> Result<(), Box<dyn Error>> {
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        let tx = self.conn.transaction()?;
+        {
+            let mut upsert = tx.prepare(
+                "INSERT INTO items (id, item_type, subreddit, title, content, upvotes, num_comments,
+                                    permalink, created_utc, source, author, recipient, first_seen, last_seen)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?13)
+                 ON CONFLICT(id) DO UPDATE SET
+                     item_type = excluded.item_type,
+                     subreddit = excluded.subreddit,
+                     title = excluded.title,
+                     content = CASE WHEN items.overwritten_at IS NULL THEN excluded.content ELSE items.content END,
+                     upvotes = excluded.upvotes,
+                     num_comments = excluded.num_comments,
+                     permalink = excluded.permalink,
+                     created_utc = excluded.created_utc,
+                     source = excluded.source,
+                     author = excluded.author,
+                     recipient = excluded.recipient,
+                     last_seen = excluded.last_seen",
+            )?;
+            let mut record_score = tx.prepare(
+                "INSERT INTO score_history (id, recorded_at, upvotes)
+                 SELECT ?1, ?2, ?3
+                 WHERE ?3 IS NOT (SELECT upvotes FROM score_history WHERE id = ?1 ORDER BY rowid DESC LIMIT 1)",
+            )?;
+            for item in items {
+                upsert.execute(params![
+                    item.id,
+                    item.item_type,
+                    item.subreddit,
+                    item.title,
+                    item.content,
+                    item.upvotes,
+                    item.num_comments,
+                    item.permalink,
+                    item.created_utc,
+                    item.source,
+                    item.author,
+                    item.recipient,
+                    now,
+                ])?;
+                record_score.execute(params![item.id, now, item.upvotes])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Stamp an item as overwritten or deleted on Reddit.
+    pub fn mark(&mut self, id: &str, action: JournalAction) -> Result<(), Box<dyn Error>> {
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+        let sql = match action {
+            JournalAction::Overwrite => "UPDATE items SET overwritten_at = ?2 WHERE id = ?1",
+            JournalAction::Delete => "UPDATE items SET deleted_at = ?2 WHERE id = ?1",
+        };
+        self.conn.execute(sql, params![id, now])?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn scores(archive: &SqliteArchive, id: &str) -> Vec<i32> {
+        let mut stmt = archive
+            .conn
+            .prepare("SELECT upvotes FROM score_history WHERE id = ?1 ORDER BY rowid")
+            .unwrap();
+        stmt.query_map([id], |row| row.get(0)).unwrap().map(|r| r.unwrap()).collect()
+    }
+
+    #[test]
+    fn upsert_keeps_first_seen_and_records_score_changes() -> Result<(), Box<dyn Error>> {
+        let temp = tempdir()?;
+        let mut archive = SqliteArchive::open(&temp.path().join("history.db"))?;
+        let mut item = UnifiedItem::from_fullname("t3_abc");
+        item.upvotes = 5;
+
+        archive.upsert_items(&[item.clone()])?;
+        archive.upsert_items(&[item.clone()])?;
+        item.upvotes = 12;
+        archive.upsert_items(&[item.clone()])?;
+
+        assert_eq!(scores(&archive, "t3_abc"), vec![5, 12]);
+        let (count, upvotes): (i64, i32) = archive
+            .conn
+            .query_row("SELECT COUNT(*), MAX(upvotes) FROM items", [], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        assert_eq!((count, upvotes), (1, 12));
+        Ok(())
+    }
+
+    #[test]
+    fn overwritten_items_keep_their_original_content() -> Result<(), Box<dyn Error>> {
+        let temp = tempdir()?;
+        let mut archive = SqliteArchive::open(&temp.path().join("history.db"))?;
+        let mut item = UnifiedItem::from_fullname("t1_abc");
+        item.content = "original".to_string();
+        archive.upsert_items(&[item.clone()])?;
+
+        archive.mark("t1_abc", JournalAction::Overwrite)?;
+        archive.mark("t1_abc", JournalAction::Delete)?;
+        item.content = "[removed]".to_string();
+        archive.upsert_items(&[item])?;
+
+        let (content, overwritten, deleted): (String, Option<String>, Option<String>) = archive.conn.query_row(
+            "SELECT content, overwritten_at, deleted_at FROM items WHERE id = 't1_abc'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        assert_eq!(content, "original");
+        assert!(overwritten.is_some() && deleted.is_some());
+        Ok(())
+    }
+}
@@ -0,0 +1,369 @@
+// OAuth token handling.
+//
+// `bydit login` runs Reddit's authorization-code flow: the user approves Bydit in the browser,
+// Reddit redirects to a loopback listener on `redirect_uri`, and the returned code is exchanged
+// for a permanent refresh token, stored in the config directory. Later runs mint access tokens
+// from that refresh token, so `config.toml` no longer needs the account password.
+use crate::config::{config_dir, Config};
+use crate::totp;
+use reqwest::header::{HeaderMap, HeaderValue, USER_AGENT};
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+
+pub const REFRESH_TOKEN_FILE: &str = "refresh_token";
+pub const DEFAULT_REDIRECT_URI: &str = "http://localhost:65010/authorize_callback";
+
+const AUTHORIZE_URL: &str = "https://www.reddit.com/api/v1/authorize";
+const ACCESS_TOKEN_URL: &str = "https://www.reddit.com/api/v1/access_token";
+
+/// Everything Bydit reads or changes: listings, api/info (`read`, `history`), edits and deletes
+/// (`edit`), `api/unsave` (`save`), `api/vote` (`vote`), `api/unhide` (Reddit files it under
+/// `report`) and private messages.
+const SCOPES: &str = "identity history read edit save vote report privatemessages";
+
+#[derive(Deserialize, Debug)]
+pub struct TokenResponse {
+    pub access_token: String,
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// Lifetime of the access token in seconds.
+    #[serde(default)]
+    pub expires_in: u64,
+}
+
+/// How an access token is obtained from Reddit.
+pub enum Grant<'a> {
+    Password { username: &'a str, password: &'a str },
+    RefreshToken(&'a str),
+    AuthorizationCode { code: &'a str, redirect_uri: &'a str },
+}
+
+/// Long-lived credentials from which access tokens are minted.
+#[derive(Clone, Debug)]
+pub enum Credentials {
+    RefreshToken(String),
+    Password { username: String, password: String, otp: OneTimePassword },
+}
+
+/// Where the two-factor code appended to the password (`password:OTP`) comes from.
+#[derive(Clone, Debug)]
+pub enum OneTimePassword {
+    /// The account has no two-factor authentication.
+    None,
+    /// Generated from the decoded `totp_secret`.
+    Totp(Vec<u8>),
+    /// Asked for on the terminal at login. A code cannot be asked for mid-run, so renewing
+    /// the token later fails with an error pointing to `totp_secret`.
+    Prompt,
+}
+
+/// Obtains access tokens for the app configured in `config.toml`, at login and whenever the
+/// current token expires.
+pub struct Authenticator {
+    config: Config,
+    credentials: Credentials,
+    /// Set once the first token was obtained, after which `OneTimePassword::Prompt` no longer asks.
+    logged_in: AtomicBool,
+}
+
+impl Authenticator {
+    pub fn new(config: &Config, credentials: Credentials) -> Authenticator {
+        Authenticator { config: config.clone(), credentials, logged_in: AtomicBool::new(false) }
+    }
+
+    pub fn uses_refresh_token(&self) -> bool {
+        matches!(self.credentials, Credentials::RefreshToken(_))
+    }
+
+    pub async fn fetch_token(&self) -> Result<TokenResponse, Box<dyn Error>> {
+        let token = match &self.credentials {
+            Credentials::RefreshToken(refresh_token) => {
+                request_token(&self.config, Grant::RefreshToken(refresh_token)).await?
+            }
+            Credentials::Password { username, password, otp } => {
+                let password = self.password_with_code(username, password, otp)?;
+                request_token(&self.config, Grant::Password { username, password: &password }).await?
+            }
+        };
+        self.logged_in.store(true, Ordering::Relaxed);
+        Ok(token)
+    }
+
+    /// The password as sent in the password grant, with the two-factor code appended if needed.
+    fn password_with_code(&self, username: &str, password: &str, otp: &OneTimePassword) -> Result<String, Box<dyn Error>> {
+        match otp {
+            OneTimePassword::None => Ok(password.to_string()),
+            OneTimePassword::Totp(key) => Ok(format!("{}:{}", password, totp::current_code(key)?)),
+            OneTimePassword::Prompt if self.logged_in.load(Ordering::Relaxed) => Err(
+                "the access token expired and a new two-factor code is needed, but --otp-prompt only asks at login. \
+                 Set totp_secret in config.toml (or use `bydit login`) for runs that outlast the access token (about an hour)"
+                    .into(),
+            ),
+            OneTimePassword::Prompt => Ok(format!("{}:{}", password, prompt_for_code(username)?)),
+        }
+    }
+}
+
+/// Ask for the current code of the account's authenticator app.
+fn prompt_for_code(username: &str) -> Result<String, Box<dyn Error>> {
+    eprint!("Two-factor code for u/{}: ", username);
+    io::stderr().flush()?;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    let code = input.trim();
+    if code.is_empty() || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Err("The two-factor code must consist of digits".into());
+    }
+    Ok(code.to_string())
+}
+
+/// Exchange a grant for an access token at Reddit's token endpoint.
+pub async fn request_token(config: &Config, grant: Grant<'_>) -> Result<TokenResponse, Box<dyn Error>> {
+    let form: Vec<(&str, &str)> = match grant {
+        Grant::Password { username, password } => {
+            vec![("grant_type", "password"), ("username", username), ("password", password)]
+        }
+        Grant::RefreshToken(refresh_token) => vec![("grant_type", "refresh_token"), ("refresh_token", refresh_token)],
+        Grant::AuthorizationCode { code, redirect_uri } => {
+            vec![("grant_type", "authorization_code"), ("code", code), ("redirect_uri", redirect_uri)]
+        }
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(USER_AGENT, HeaderValue::from_str(&config.user_agent)?);
+    let client = Client::builder().default_headers(headers).build()?;
+    let response = client
+        .post(ACCESS_TOKEN_URL)
+        .basic_auth(&config.client_id, Some(&config.client_secret))
+        .form(&form)
+        .send()
+        .await?;
+
+    let status = response.status();
+    let body: serde_json::Value = response.json().await.unwrap_or(serde_json::Value::Null);
+    // Reddit reports bad credentials or revoked tokens as `{"error": ...}`, sometimes with a 200
+    if let Some(error) = body.get("error") {
+        return Err(format!("Reddit rejected the token request ({}): {}", status, error).into());
+    }
+    if !status.is_success() {
+        return Err(format!("Token request failed with status {}", status).into());
+    }
+    Ok(serde_json::from_value(body)?)
+}
+
+/// Location of the stored refresh token: `<config dir>/refresh_token`, or
+/// `<config dir>/refresh_token-<profile>` for a named profile.
+pub fn refresh_token_path(profile: Option<&str>) -> Option<PathBuf> {
+    let file_name = match profile {
+        Some(profile) => format!("{}-{}", REFRESH_TOKEN_FILE, profile),
+        None => REFRESH_TOKEN_FILE.to_string(),
+    };
+    config_dir().map(|dir| dir.join(file_name))
+}
+
+/// Read a stored refresh token. A missing file means `bydit login` has not been run.
+pub fn load_refresh_token(path: &Path) -> Result<Option<String>, Box<dyn Error>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => {
+            let token = contents.trim();
+            Ok((!token.is_empty()).then(|| token.to_string()))
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => {
+            let err_msg = format!("Failed to read refresh token '{}': {}", path.display(), err);
+            Err(Box::new(io::Error::new(err.kind(), err_msg)))
+        }
+    }
+}
+
+/// Write the refresh token, readable by the current user only.
+fn save_refresh_token(path: &Path, token: &str) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).map_err(|e| {
+        let err_msg = format!("Failed to write refresh token '{}': {}", path.display(), e);
+        Box::new(io::Error::new(e.kind(), err_msg)) as Box<dyn Error>
+    })?;
+    writeln!(file, "{}", token)?;
+    // `mode` only applies to new files; tighten one left behind with wider permissions
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+    Ok(())
+}
+
+fn authorize_url(client_id: &str, redirect_uri: &str, state: &str) -> Result<Url, Box<dyn Error>> {
+    Ok(Url::parse_with_params(
+        AUTHORIZE_URL,
+        &[
+            ("client_id", client_id),
+            ("response_type", "code"),
+            ("state", state),
+            ("redirect_uri", redirect_uri),
+            ("duration", "permanent"),
+            ("scope", SCOPES),
+        ],
+    )?)
+}
+
+/// Extract the authorization code from the request line of the redirect, e.g.
+/// `GET /authorize_callback?state=...&code=... HTTP/1.1`.
+/// Returns `Ok(None)` for requests to other paths, such as the browser asking for a favicon.
+fn parse_callback(request_line: &str, callback_path: &str, expected_state: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let target = request_line.split_whitespace().nth(1).ok_or("Malformed request from browser")?;
+    let url = Url::parse("http://localhost")?.join(target)?;
+    if url.path() != callback_path {
+        return Ok(None);
+    }
+
+    let param = |name: &str| url.query_pairs().find(|(key, _)| key == name).map(|(_, value)| value.into_owned());
+    if let Some(error) = param("error") {
+        return Err(format!("Authorization was not granted: {}", error).into());
+    }
+    if param("state").as_deref() != Some(expected_state) {
+        return Err("Authorization response has an unexpected state; aborting".into());
+    }
+    param("code").map(Some).ok_or_else(|| "Authorization response contains no code".into())
+}
+
+/// Accept connections on the loopback redirect URI until Reddit's redirect arrives.
+async fn wait_for_callback(redirect: &Url, listener: TcpListener, state: &str) -> Result<String, Box<dyn Error>> {
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let mut request_line = String::new();
+        BufReader::new(&mut stream).read_line(&mut request_line).await?;
+
+        let (status, message, result) = match parse_callback(&request_line, redirect.path(), state) {
+            Ok(Some(code)) => ("200 OK", "Bydit is authorized. You can close this tab.", Some(Ok(code))),
+            Ok(None) => ("404 Not Found", "Not found.", None),
+            Err(e) => ("400 Bad Request", "Authorization failed, see the terminal for details.", Some(Err(e))),
+        };
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            message.len(),
+            message
+        );
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await.ok();
+
+        if let Some(result) = result {
+            return result;
+        }
+    }
+}
+
+/// Run the authorization-code flow and store the resulting refresh token.
+pub async fn login(config: &Config, debug_mode: bool) -> Result<(), Box<dyn Error>> {
+    let redirect = Url::parse(&config.redirect_uri)?;
+    let host = redirect.host_str().unwrap_or_default();
+    if !matches!(host, "localhost" | "127.0.0.1" | "[::1]") {
+        return Err(format!(
+            "redirect_uri '{}' must point to localhost so Bydit can receive the authorization",
+            config.redirect_uri
+        )
+        .into());
+    }
+    let port = redirect.port_or_known_default().ok_or("redirect_uri has no port")?;
+    let listener = TcpListener::bind((host.trim_matches(['[', ']']), port)).await.map_err(|e| {
+        format!("Failed to listen on {}:{} for the authorization redirect: {}", host, port, e)
+    })?;
+    if debug_mode {
+        println!("Listening for the authorization redirect on {}", config.redirect_uri);
+    }
+
+    let state: String = std::iter::repeat_with(fastrand::alphanumeric).take(24).collect();
+    let url = authorize_url(&config.client_id, &config.redirect_uri, &state)?;
+    eprintln!("Open this URL in your browser and allow access:\n\n  {}\n", url);
+    eprintln!("Waiting for Reddit to redirect to {} ...", config.redirect_uri);
+
+    let code = wait_for_callback(&redirect, listener, &state).await?;
+    let token = request_token(
+        config,
+        Grant::AuthorizationCode { code: &code, redirect_uri: &config.redirect_uri },
+    )
+    .await?;
+    let refresh_token = token.refresh_token.ok_or("Reddit returned no refresh token")?;
+
+    let path = refresh_token_path(config.profile.as_deref()).ok_or("Could not determine the config directory (HOME is unset)")?;
+    save_refresh_token(&path, &refresh_token)?;
+    eprintln!("Logged in. Refresh token stored in {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn otp_prompt_does_not_ask_again_on_renewal() -> Result<(), Box<dyn Error>> {
+        let config: Config = toml::from_str("user_agent = \"ua\"\nclient_id = \"id\"\nclient_secret = \"\"\nusername = \"someone\"\n")?;
+        let authenticator = Authenticator::new(&config, Credentials::RefreshToken("token".to_string()));
+        authenticator.logged_in.store(true, Ordering::Relaxed);
+        let error = authenticator.password_with_code("someone", "pw", &OneTimePassword::Prompt).unwrap_err();
+        assert!(error.to_string().contains("totp_secret"));
+        assert_eq!(authenticator.password_with_code("someone", "pw", &OneTimePassword::None)?, "pw");
+        Ok(())
+    }
+
+    #[test]
+    fn callback_yields_code_for_matching_state() -> Result<(), Box<dyn Error>> {
+        let line = "GET /authorize_callback?state=abc&code=XYZ123 HTTP/1.1\r\n";
+        assert_eq!(parse_callback(line, "/authorize_callback", "abc")?, Some("XYZ123".to_string()));
+        assert_eq!(parse_callback("GET /favicon.ico HTTP/1.1", "/authorize_callback", "abc")?, None);
+        assert!(parse_callback(line, "/authorize_callback", "other").is_err());
+        assert!(parse_callback("GET /authorize_callback?state=abc&error=access_denied HTTP/1.1", "/authorize_callback", "abc").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn authorize_url_requests_a_permanent_token() -> Result<(), Box<dyn Error>> {
+        let url = authorize_url("client", DEFAULT_REDIRECT_URI, "state")?;
+        let query: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        assert!(query.contains(&("duration".to_string(), "permanent".to_string())));
+        assert!(query.contains(&("redirect_uri".to_string(), DEFAULT_REDIRECT_URI.to_string())));
+        Ok(())
+    }
+
+    #[test]
+    fn refresh_token_round_trips() -> Result<(), Box<dyn Error>> {
+        let temp = tempdir()?;
+        let path = temp.path().join("bydit").join(REFRESH_TOKEN_FILE);
+        assert_eq!(load_refresh_token(&path)?, None);
+        save_refresh_token(&path, "token-value")?;
+        assert_eq!(load_refresh_token(&path)?, Some("token-value".to_string()));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn existing_refresh_token_file_is_made_owner_only() -> Result<(), Box<dyn Error>> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempdir()?;
+        let path = temp.path().join(REFRESH_TOKEN_FILE);
+        fs::write(&path, "old")?;
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644))?;
+        save_refresh_token(&path, "token-value")?;
+        assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+        Ok(())
+    }
+}
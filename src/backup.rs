@@ -0,0 +1,157 @@
+// Local archive of items as they were right before Bydit overwrote or deleted them.
+//
+// Records are appended as JSON lines to `backups.jsonl` in the data directory, so the file
+// survives across runs and can be inspected with `bydit backups list|search`.
+use crate::journal::JournalAction;
+use crate::models::UnifiedItem;
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+pub const BACKUP_FILE: &str = "backups.jsonl";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BackupRecord {
+    pub backed_up_at: String,
+    pub action: JournalAction,
+    pub item: UnifiedItem,
+}
+
+impl BackupRecord {
+    /// Case-insensitive substring match against the id, subreddit, title, body and permalink.
+    pub fn matches(&self, needle: &str) -> bool {
+        let needle = needle.to_lowercase();
+        [
+            &self.item.id,
+            &self.item.subreddit,
+            &self.item.title,
+            &self.item.content,
+            &self.item.permalink,
+        ]
+        .iter()
+        .any(|field| field.to_lowercase().contains(&needle))
+    }
+}
+
+pub struct BackupArchive {
+    path: PathBuf,
+    file: File,
+}
+
+impl BackupArchive {
+    /// Open `<data_dir>/backups.jsonl` for appending, creating it if needed.
+    pub fn open(data_dir: &Path) -> Result<BackupArchive, Box<dyn Error>> {
+        fs::create_dir_all(data_dir).map_err(|e| {
+            let err_msg = format!("Failed to create data directory '{}': {}", data_dir.display(), e);
+            Box::new(io::Error::new(e.kind(), err_msg)) as Box<dyn Error>
+        })?;
+        let path = data_dir.join(BACKUP_FILE);
+        // The records hold the full original text, so keep them readable only by the owner
+        let mut options = OpenOptions::new();
+        options.create(true).append(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let file = options.open(&path).map_err(|e| {
+            let err_msg = format!("Failed to open backup file '{}': {}", path.display(), e);
+            Box::new(io::Error::new(e.kind(), err_msg)) as Box<dyn Error>
+        })?;
+        Ok(BackupArchive { path, file })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append the item as it currently is, before `action` is applied to it.
+    pub fn append(&mut self, item: &UnifiedItem, action: JournalAction) -> Result<(), Box<dyn Error>> {
+        let record = BackupRecord {
+            backed_up_at: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            action,
+            item: item.clone(),
+        };
+        writeln!(self.file, "{}", serde_json::to_string(&record)?)?;
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// Read every record from a backup file. A missing file simply means nothing was backed up yet.
+pub fn read_backups(path: &Path) -> Result<Vec<BackupRecord>, Box<dyn Error>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => {
+            let err_msg = format!("Failed to read backup file '{}': {}", path.display(), err);
+            return Err(Box::new(io::Error::new(err.kind(), err_msg)));
+        }
+    };
+
+    let mut records = Vec::new();
+    for (line_number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let record: BackupRecord = serde_json::from_str(&line).map_err(|e| {
+            let err_msg = format!(
+                "Failed to parse backup file '{}' at line {}: {}",
+                path.display(),
+                line_number + 1,
+                e
+            );
+            Box::new(io::Error::new(ErrorKind::InvalidData, err_msg)) as Box<dyn Error>
+        })?;
+        records.push(record);
+    }
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn appended_records_can_be_read_and_searched() -> Result<(), Box<dyn Error>> {
+        let temp = tempdir()?;
+        let mut item = UnifiedItem::from_fullname("t1_abc");
+        item.subreddit = "rust".to_string();
+        item.content = "Original text mentioning ACME Corp".to_string();
+
+        let mut archive = BackupArchive::open(temp.path())?;
+        archive.append(&item, JournalAction::Overwrite)?;
+        archive.append(&item, JournalAction::Delete)?;
+
+        let records = read_backups(archive.path())?;
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].item.content, item.content);
+        assert_eq!(records[1].action, JournalAction::Delete);
+        assert!(records[0].matches("acme corp"));
+        assert!(!records[0].matches("golang"));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn backup_file_is_written_owner_only() -> Result<(), Box<dyn Error>> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempdir()?;
+        let archive = BackupArchive::open(temp.path())?;
+        assert_eq!(fs::metadata(archive.path())?.permissions().mode() & 0o777, 0o600);
+        Ok(())
+    }
+
+    #[test]
+    fn missing_backup_file_is_empty() -> Result<(), Box<dyn Error>> {
+        let temp = tempdir()?;
+        assert!(read_backups(&temp.path().join(BACKUP_FILE))?.is_empty());
+        Ok(())
+    }
+}
@@ -1,8 +1,11 @@
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about, long_about = None, allow_negative_numbers = true)]
 pub struct Cli {
+    #[clap(subcommand)]
+    pub command: Option<Command>,
+
     #[clap(short, long, value_parser, help = "Filter by subreddit(s). Comma-separated list: --subreddit one,two,three")]
     pub subreddit: Option<String>,
 
@@ -18,21 +21,75 @@ pub struct Cli {
     #[clap(short, long, value_parser)] // Long flag will be --item-type
     pub item_type: Option<String>,
 
+    #[clap(long, value_enum, value_delimiter = ',', conflicts_with = "gdpr_export", help = "Listing(s) to fetch, comma-separated, including the inbox, sent and messages private message folders (default: submitted,comments as selected by --item-type)")]
+    pub source: Vec<Source>,
+
+    #[clap(long, value_enum, value_delimiter = ',', value_name = "FLAGS", help = "Keep only items with all of these flags set, comma-separated")]
+    pub with_flag: Vec<ItemFlag>,
+
+    #[clap(long, value_enum, value_delimiter = ',', value_name = "FLAGS", help = "Drop items with any of these flags set, comma-separated")]
+    pub without_flag: Vec<ItemFlag>,
+
+    #[clap(long, global = true, value_parser, help = "Use the [profiles.<name>] account from the config file")]
+    pub profile: Option<String>,
+
+    #[clap(long, conflicts_with_all = ["profile", "resume"], help = "Run the same fetch, filters and actions for every profile in the config file")]
+    pub all_profiles: bool,
+
+    #[clap(long, global = true, help = "Refuse to use a config file that other users can read or that belongs to another user")]
+    pub strict_permissions: bool,
+
+    #[clap(long, global = true, help = "Ask for a two-factor code when logging in with username and password (not needed with totp_secret or bydit login). Asked once: runs that outlast the access token need totp_secret")]
+    pub otp_prompt: bool,
+
     #[clap(long, help = "Enable debug mode for verbose output")]
     pub debug: bool,
 
     #[clap(short, long, action)]
     pub delete: bool,
 
-    #[clap(short, long, help = "Skip confirmation prompts when deleting items")]
+    #[clap(long, help = "Remove every fetched item from your saved items (api/unsave)")]
+    pub unsave: bool,
+
+    #[clap(long, help = "Clear your vote on every fetched item (api/vote with dir=0)")]
+    pub unvote: bool,
+
+    #[clap(long, help = "Unhide every fetched item (api/unhide)")]
+    pub unhide: bool,
+
+    #[clap(short, long, global = true, help = "Skip confirmation prompts when deleting, unsaving, unvoting or unhiding items")]
     pub yes: bool,
 
-    #[clap(long, value_parser, name = "overwrite_text")]
+    #[clap(long, global = true, help = "Show what --overwrite, --delete, --unsave, --unvote, --unhide and apply-policy rules would do without changing anything on Reddit")]
+    pub dry_run: bool,
+
+    #[clap(long, global = true, value_name = "PATH", value_parser, help = "Protect list of items no action may change (default: protect.txt in the config directory)")]
+    pub protect_file: Option<String>,
+
+    #[clap(long, global = true, value_name = "N", value_parser, allow_hyphen_values = true, help = "Never change items with a score above N")]
+    pub protect_score_above: Option<i32>,
+
+    #[clap(long, global = true, help = "Never change items that were awarded gold")]
+    pub protect_gilded: bool,
+
+    #[clap(long, value_parser, value_name = "JOURNAL", help = "Resume an interrupted --overwrite/--delete run from its journal file, retrying only pending or failed items")]
+    pub resume: Option<String>,
+
+    #[clap(long, value_parser, name = "overwrite_text", help = "Replace the content of every matched post and comment with this text. Archived items cannot be edited and are skipped (--delete still deletes them)")]
     pub overwrite: Option<String>,
 
-    #[clap(long, value_parser, help = "Export items to a CSV file at the specified path")]
+    #[clap(long, value_parser, conflicts_with_all = ["format", "output"], help = "Export items to a CSV file at the specified path (same as --format csv --output PATH)")]
     pub csv: Option<String>,
 
+    #[clap(long, value_enum, default_value_t = OutputFormat::Csv, help = "Output format for the fetched items")]
+    pub format: OutputFormat,
+
+    #[clap(short = 'o', long, value_parser, help = "Write the output to this file instead of stdout")]
+    pub output: Option<String>,
+
+    #[clap(long, value_parser, value_name = "PATH", help = "Also upsert every fetched item into a SQLite archive at PATH, tracking first/last seen, score history and overwrites/deletions")]
+    pub sqlite: Option<String>,
+
     #[clap(long, value_parser, help = "Minimum age of items (e.g., '1 week', '2 years', or '2024-01-15')")]
     pub min_age: Option<String>,
 
@@ -41,4 +98,169 @@ pub struct Cli {
 
     #[clap(short = 'p', long, value_parser, help = "Filter comments by post title (case-insensitive substring match)")]
     pub post_title: Option<String>,
+
+    #[clap(long = "where", value_name = "EXPR", value_parser, help = "Keep only items matching a filter expression, e.g. 'subreddit in (rust, golang) and score < 2 and age > 1y'")]
+    pub where_expr: Option<String>,
+
+    #[clap(long, value_name = "REGEX", value_parser, help = "Keep only items whose body matches this regular expression")]
+    pub content_regex: Option<String>,
+
+    #[clap(long, value_name = "REGEX", value_parser, help = "Drop items whose body matches this regular expression")]
+    pub exclude_content_regex: Option<String>,
+
+    #[clap(long, value_name = "REGEX", value_parser, help = "Keep only items whose title (a comment's post title) matches this regular expression")]
+    pub title_regex: Option<String>,
+
+    #[clap(long, value_name = "REGEX", value_parser, help = "Drop items whose title (a comment's post title) matches this regular expression")]
+    pub exclude_title_regex: Option<String>,
+
+    #[clap(long, value_name = "PATH", value_parser, help = "Keep only items mentioning any term of this file (one per line) in their body or own title")]
+    pub keyword_file: Option<String>,
+
+    #[clap(long, value_name = "PATH", value_parser, help = "Drop items mentioning any term of this file (one per line) in their body or own title")]
+    pub exclude_keyword_file: Option<String>,
+
+    #[clap(long, help = "Make the regex and keyword filters case-sensitive")]
+    pub case_sensitive: bool,
+
+    #[clap(long, help = "Only match keywords as whole words")]
+    pub whole_word: bool,
+
+    #[clap(long, value_parser, help = "Stop each listing after this many pages of 100 items (default: follow every page)")]
+    pub max_pages: Option<u32>,
+
+    #[clap(long, help = "Walk the new, top (every time window), controversial and hot listings to reach items beyond Reddit's ~1000-item cap")]
+    pub all_sorts: bool,
+
+    #[clap(long, value_parser, help = "Read items from Reddit's data request export (ZIP file or extracted directory) instead of the listings")]
+    pub gdpr_export: Option<String>,
+}
+
+/// A listing of the authenticated account that items can be fetched from.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Source {
+    /// Your posts
+    Submitted,
+    /// Your comments
+    Comments,
+    /// Posts and comments you saved
+    Saved,
+    /// Posts and comments you upvoted
+    Upvoted,
+    /// Posts and comments you downvoted
+    Downvoted,
+    /// Posts you hid
+    Hidden,
+    /// Your posts and comments that received awards
+    Gilded,
+    /// Private messages you received
+    Inbox,
+    /// Private messages you sent
+    Sent,
+    /// Private message conversations, sent and received
+    Messages,
+}
+
+impl Source {
+    /// Name of the listing under `user/<name>/`, or under `message/` for the private message
+    /// sources. Also stored in `UnifiedItem::source`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Source::Submitted => "submitted",
+            Source::Comments => "comments",
+            Source::Saved => "saved",
+            Source::Upvoted => "upvoted",
+            Source::Downvoted => "downvoted",
+            Source::Hidden => "hidden",
+            Source::Gilded => "gilded",
+            Source::Inbox => "inbox",
+            Source::Sent => "sent",
+            Source::Messages => "messages",
+        }
+    }
+
+    /// Whether the listing lives under `message/` and holds private messages.
+    pub fn is_message(&self) -> bool {
+        matches!(self, Source::Inbox | Source::Sent | Source::Messages)
+    }
+
+    /// Whether the listing holds the user's own posts or comments, rather than a mix of
+    /// items by anyone.
+    pub fn is_own_content(&self) -> bool {
+        matches!(self, Source::Submitted | Source::Comments)
+    }
+}
+
+/// Status flags of posts and comments, for `--with-flag` and `--without-flag`.
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum ItemFlag {
+    /// Edited after posting
+    Edited,
+    /// Pinned by a moderator
+    Stickied,
+    /// Locked by a moderator
+    Locked,
+    /// Marked NSFW
+    Nsfw,
+    /// Marked as a spoiler (posts only)
+    Spoiler,
+    /// Archived by Reddit; can no longer be edited
+    Archived,
+    /// Distinguished as moderator or admin
+    Distinguished,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Csv,
+    /// A single JSON array
+    Json,
+    /// One JSON object per line
+    Ndjson,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Create a config file interactively, check the credentials and save it to ~/.config/bydit
+    Init,
+    /// Authorize Bydit in the browser and store a refresh token, so config.toml needs no password
+    Login,
+    /// Manage the config file
+    Config {
+        #[clap(subcommand)]
+        action: ConfigCommand,
+    },
+    /// Apply the overwrite, delete and export rules of a retention policy file in one run
+    ApplyPolicy {
+        /// TOML file of [[rule]] tables
+        #[clap(value_name = "RULES")]
+        rules: String,
+    },
+    /// Inspect the copies of items saved right before they were overwritten or deleted
+    Backups {
+        #[clap(subcommand)]
+        action: BackupsCommand,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Restrict the config file to mode 0600 so only its owner can read it
+    FixPermissions,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum BackupsCommand {
+    /// List backed-up items, most recent first
+    List {
+        #[clap(short = 'n', long, value_parser, help = "Only show the N most recent backups")]
+        limit: Option<usize>,
+    },
+    /// Show backed-up items whose id, subreddit, title, content or permalink contain TEXT (case-insensitive)
+    Search {
+        text: String,
+
+        #[clap(short = 'n', long, value_parser, help = "Only show the N most recent matches")]
+        limit: Option<usize>,
+    },
 }
@@ -1,31 +1,115 @@
+use crate::api::RetryPolicy;
+use crate::auth::DEFAULT_REDIRECT_URI;
 use serde::Deserialize;
 use std::env;
 use std::error::Error;
 use std::fs;
 use std::io::{self, ErrorKind};
 use std::path::{Path, PathBuf};
-use toml;
 
 const APPLICATION_DIR: &str = "bydit";
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Debug, Clone)]
 pub struct Config {
     pub user_agent: String,
     pub client_id: String,
+    #[serde(default)]
     pub client_secret: String,
+    /// Only needed for the password grant; `bydit login` stores a refresh token instead.
+    #[serde(default)]
     pub username: String,
+    #[serde(default)]
     pub password: String,
+    /// Base32 secret of the account's two-factor authentication, for the password grant.
+    #[serde(default)]
+    pub totp_secret: Option<String>,
+    /// Refresh token given directly (or through the sources below) instead of `bydit login`.
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// Commands printing a secret (e.g. `pass show reddit`), run instead of reading the plain value.
+    #[serde(default)]
+    pub client_secret_command: Option<String>,
+    #[serde(default)]
+    pub password_command: Option<String>,
+    #[serde(default)]
+    pub totp_secret_command: Option<String>,
+    #[serde(default)]
+    pub refresh_token_command: Option<String>,
+    /// Look secrets up in the Secret Service keyring with `secret-tool`.
+    #[serde(default)]
+    pub use_keyring: bool,
+    /// Redirect URI registered for the Reddit app, used by `bydit login`.
+    #[serde(default = "default_redirect_uri")]
+    pub redirect_uri: String,
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    /// Name of the `[profiles.<name>]` table this account came from, if any.
+    #[serde(skip)]
+    pub profile: Option<String>,
 }
 
-pub fn load_config(filename: &str, debug_mode: bool) -> Result<Config, Box<dyn Error>> {
-    load_config_with_env(filename, debug_mode, EnvironmentPaths::from_process())
+impl Config {
+    /// Profile name for messages and file names; `default` for a config without profiles.
+    pub fn profile_name(&self) -> &str {
+        self.profile.as_deref().unwrap_or("default")
+    }
+}
+
+/// Every account defined in a config file. Top-level settings apply to all profiles, and each
+/// `[profiles.<name>]` table overrides them for one account. A file without profile tables
+/// describes a single, unnamed account.
+#[derive(Debug)]
+pub struct ConfigProfiles {
+    default_profile: Option<String>,
+    profiles: Vec<Config>,
+}
+
+impl ConfigProfiles {
+    /// The account for `--profile <name>`, or without one the `default_profile`, or the only
+    /// account defined.
+    pub fn select(&self, name: Option<&str>) -> Result<Config, Box<dyn Error>> {
+        let Some(name) = name.or(self.default_profile.as_deref()) else {
+            return match self.profiles.as_slice() {
+                [config] => Ok(config.clone()),
+                _ => Err(format!(
+                    "Config defines several profiles ({}); pass --profile <name> or set default_profile",
+                    self.names()
+                )
+                .into()),
+            };
+        };
+        self.profiles
+            .iter()
+            .find(|config| config.profile.as_deref() == Some(name))
+            .cloned()
+            .ok_or_else(|| format!("Profile '{}' not found in config (available: {})", name, self.names()).into())
+    }
+
+    pub fn all(&self) -> &[Config] {
+        &self.profiles
+    }
+
+    fn names(&self) -> String {
+        self.profiles.iter().map(|config| config.profile_name()).collect::<Vec<_>>().join(", ")
+    }
+}
+
+fn default_redirect_uri() -> String {
+    DEFAULT_REDIRECT_URI.to_string()
+}
+
+/// Load the config file, warning (or with `strict_permissions` refusing) when other users
+/// could read the credentials in it.
+pub fn load_config(filename: &str, strict_permissions: bool, debug_mode: bool) -> Result<ConfigProfiles, Box<dyn Error>> {
+    load_config_with_env(filename, strict_permissions, debug_mode, EnvironmentPaths::from_process())
 }
 
 fn load_config_with_env(
     filename: &str,
+    strict_permissions: bool,
     debug_mode: bool,
     env_paths: EnvironmentPaths,
-) -> Result<Config, Box<dyn Error>> {
+) -> Result<ConfigProfiles, Box<dyn Error>> {
     let mut searched_paths = Vec::new();
 
     for candidate in candidate_paths_with_env(filename, &env_paths) {
@@ -34,6 +118,7 @@ fn load_config_with_env(
                 if debug_mode {
                     eprintln!("Using config file at {}", candidate.display());
                 }
+                check_permissions(&candidate, strict_permissions)?;
                 return parse_config(&config_str, &candidate, debug_mode);
             }
             Err(err) if err.kind() == ErrorKind::NotFound => {
@@ -61,12 +146,72 @@ fn load_config_with_env(
     Err(Box::new(io::Error::new(ErrorKind::NotFound, message)))
 }
 
+/// Restrict the config file found for `filename` to mode 0600. Returns its path.
+pub fn fix_config_permissions(filename: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let candidates = candidate_paths_with_env(filename, &EnvironmentPaths::from_process());
+    let path = candidates.iter().find(|candidate| candidate.is_file()).ok_or_else(|| {
+        format!("Failed to locate config file '{}'. Checked paths: {}", filename, format_paths(&candidates))
+    })?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600)).map_err(|e| {
+            let err_msg = format!("Failed to change permissions of '{}': {}", path.display(), e);
+            Box::new(io::Error::new(e.kind(), err_msg)) as Box<dyn Error>
+        })?;
+    }
+    Ok(path.clone())
+}
+
+#[cfg(unix)]
+fn check_permissions(path: &Path, strict_permissions: bool) -> Result<(), Box<dyn Error>> {
+    use std::os::unix::fs::MetadataExt;
+
+    let metadata = fs::metadata(path)?;
+    // SAFETY: geteuid has no preconditions and cannot fail
+    let euid = unsafe { libc::geteuid() };
+    let problems = permission_problems(metadata.mode(), metadata.uid(), euid);
+    if problems.is_empty() {
+        return Ok(());
+    }
+
+    let message = format!(
+        "Config file '{}' {}. Run `bydit config fix-permissions` to restrict it to mode 0600.",
+        path.display(),
+        problems.join(" and ")
+    );
+    if strict_permissions {
+        return Err(Box::new(io::Error::new(ErrorKind::PermissionDenied, message)));
+    }
+    eprintln!("Warning: {}", message);
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_permissions(_path: &Path, _strict_permissions: bool) -> Result<(), Box<dyn Error>> {
+    Ok(())
+}
+
+/// Reasons why a config file with this mode and owner could leak its credentials.
+#[cfg(unix)]
+fn permission_problems(mode: u32, owner: u32, euid: u32) -> Vec<String> {
+    let mut problems = Vec::new();
+    if mode & 0o044 != 0 {
+        problems.push(format!("is readable by other users (mode {:04o})", mode & 0o7777));
+    }
+    if owner != euid {
+        problems.push(format!("is owned by uid {}, not the current user (uid {})", owner, euid));
+    }
+    problems
+}
+
 fn parse_config(
     contents: &str,
     origin: &Path,
     debug_mode: bool,
-) -> Result<Config, Box<dyn Error>> {
-    toml::from_str(contents).map_err(|e| {
+) -> Result<ConfigProfiles, Box<dyn Error>> {
+    let parse_error = |e: &dyn std::fmt::Display| {
         if debug_mode {
             eprintln!(
                 "Failed to parse config file '{}'. Check its format. Error: {}",
@@ -76,7 +221,37 @@ fn parse_config(
         }
         let err_msg = format!("Failed to parse config file '{}': {}", origin.display(), e);
         Box::new(io::Error::new(io::ErrorKind::InvalidData, err_msg)) as Box<dyn Error>
-    })
+    };
+
+    let mut shared: toml::Table = toml::from_str(contents).map_err(|e| parse_error(&e))?;
+    let default_profile = match shared.remove("default_profile") {
+        Some(toml::Value::String(name)) => Some(name),
+        Some(_) => return Err(parse_error(&"default_profile must be a string")),
+        None => None,
+    };
+
+    let profiles = match shared.remove("profiles") {
+        None => vec![toml::Value::Table(shared).try_into::<Config>().map_err(|e| parse_error(&e))?],
+        Some(toml::Value::Table(profile_tables)) => {
+            let mut profiles = Vec::new();
+            for (name, overrides) in profile_tables {
+                let toml::Value::Table(overrides) = overrides else {
+                    return Err(parse_error(&format!("profiles.{} must be a table", name)));
+                };
+                let mut merged = shared.clone();
+                merged.extend(overrides);
+                let mut config: Config = toml::Value::Table(merged)
+                    .try_into()
+                    .map_err(|e| parse_error(&format!("profile '{}': {}", name, e)))?;
+                config.profile = Some(name);
+                profiles.push(config);
+            }
+            profiles
+        }
+        Some(_) => return Err(parse_error(&"profiles must be a table of [profiles.<name>] tables")),
+    };
+
+    Ok(ConfigProfiles { default_profile, profiles })
 }
 
 #[derive(Clone, Debug)]
@@ -98,6 +273,45 @@ impl EnvironmentPaths {
     }
 }
 
+/// Directory for Bydit's configuration and credentials: `$XDG_CONFIG_HOME/bydit`, falling back
+/// to `~/.config/bydit`.
+pub fn config_dir() -> Option<PathBuf> {
+    config_dir_with_env(&EnvironmentPaths::from_process())
+}
+
+fn config_dir_with_env(env_paths: &EnvironmentPaths) -> Option<PathBuf> {
+    if let Some(config_home) = &env_paths.xdg_config_home {
+        Some(config_home.join(APPLICATION_DIR))
+    } else {
+        env_paths
+            .home_dir
+            .as_ref()
+            .map(|home_dir| home_dir.join(".config").join(APPLICATION_DIR))
+    }
+}
+
+/// Directory for Bydit's own state (journals, backups): `$XDG_DATA_HOME/bydit`, falling back
+/// to `~/.local/share/bydit`.
+pub fn data_dir() -> Option<PathBuf> {
+    data_dir_with_env(&EnvironmentPaths::from_process())
+}
+
+fn data_dir_with_env(env_paths: &EnvironmentPaths) -> Option<PathBuf> {
+    if let Some(data_home) = &env_paths.xdg_data_home {
+        Some(data_home.join(APPLICATION_DIR))
+    } else {
+        env_paths
+            .home_dir
+            .as_ref()
+            .map(|home_dir| home_dir.join(".local").join("share").join(APPLICATION_DIR))
+    }
+}
+
+/// The places `load_config` looks for `filename`, in the order it tries them.
+pub fn candidate_paths(filename: &str) -> Vec<PathBuf> {
+    candidate_paths_with_env(filename, &EnvironmentPaths::from_process())
+}
+
 fn candidate_paths_with_env(filename: &str, env_paths: &EnvironmentPaths) -> Vec<PathBuf> {
     let requested = Path::new(filename);
 
@@ -112,27 +326,12 @@ fn candidate_paths_with_env(filename: &str, env_paths: &EnvironmentPaths) -> Vec
         .unwrap_or_else(|| PathBuf::from("."));
     paths.push(base_dir.join(requested));
 
-    if let Some(config_home) = &env_paths.xdg_config_home {
-        paths.push(config_home.join(APPLICATION_DIR).join(requested));
-    } else if let Some(home_dir) = &env_paths.home_dir {
-        paths.push(
-            home_dir
-                .join(".config")
-                .join(APPLICATION_DIR)
-                .join(requested),
-        );
+    if let Some(config_dir) = config_dir_with_env(env_paths) {
+        paths.push(config_dir.join(requested));
     }
 
-    if let Some(data_home) = &env_paths.xdg_data_home {
-        paths.push(data_home.join(APPLICATION_DIR).join(requested));
-    } else if let Some(home_dir) = &env_paths.home_dir {
-        paths.push(
-            home_dir
-                .join(".local")
-                .join("share")
-                .join(APPLICATION_DIR)
-                .join(requested),
-        );
+    if let Some(data_dir) = data_dir_with_env(env_paths) {
+        paths.push(data_dir.join(requested));
     }
 
     paths
@@ -202,6 +401,23 @@ mod tests {
         );
     }
 
+    #[test]
+    fn data_dir_prefers_xdg_data_home() {
+        let mut env_paths = EnvironmentPaths {
+            xdg_config_home: None,
+            xdg_data_home: Some(PathBuf::from("/tmp/xdg_data")),
+            home_dir: Some(PathBuf::from("/home/demo")),
+            current_dir: None,
+        };
+        assert_eq!(data_dir_with_env(&env_paths), Some(PathBuf::from("/tmp/xdg_data/bydit")));
+
+        env_paths.xdg_data_home = None;
+        assert_eq!(
+            data_dir_with_env(&env_paths),
+            Some(PathBuf::from("/home/demo/.local/share/bydit"))
+        );
+    }
+
     #[test]
     fn load_config_reads_from_xdg_config_home() -> Result<(), Box<dyn Error>> {
         let temp = tempdir()?;
@@ -217,11 +433,68 @@ mod tests {
             current_dir: Some(temp.path().to_path_buf()),
         };
 
-        let config = load_config_with_env("config.toml", false, env_paths)?;
+        let config = load_config_with_env("config.toml", false, false, env_paths)?.select(None)?;
         assert_eq!(config.username, "user");
+        assert_eq!(config.retry, RetryPolicy::default());
         Ok(())
     }
 
+    #[test]
+    fn password_is_optional() -> Result<(), Box<dyn Error>> {
+        let contents = "user_agent = \"ua\"\nclient_id = \"id\"\nclient_secret = \"secret\"\n";
+        let config = parse_config(contents, Path::new("config.toml"), false)?.select(None)?;
+        assert!(config.password.is_empty());
+        assert_eq!(config.redirect_uri, DEFAULT_REDIRECT_URI);
+        Ok(())
+    }
+
+    #[test]
+    fn retry_table_overrides_defaults() -> Result<(), Box<dyn Error>> {
+        let contents = format!("{}\n[retry]\nmax_attempts = 2\nretryable_statuses = [503]\n", sample_config());
+        let config = parse_config(&contents, Path::new("config.toml"), false)?.select(None)?;
+        assert_eq!(config.retry.max_attempts, 2);
+        assert_eq!(config.retry.retryable_statuses, vec![503]);
+        assert_eq!(config.retry.backoff_base_ms, RetryPolicy::default().backoff_base_ms);
+        Ok(())
+    }
+
+    #[test]
+    fn profiles_override_shared_settings() -> Result<(), Box<dyn Error>> {
+        let contents = concat!(
+            "user_agent = \"ua\"\nclient_id = \"id\"\nclient_secret = \"secret\"\n",
+            "default_profile = \"work\"\n",
+            "[profiles.work]\nusername = \"work_user\"\n",
+            "[profiles.personal]\nusername = \"me\"\nclient_id = \"other\"\n",
+        );
+        let profiles = parse_config(contents, Path::new("config.toml"), false)?;
+        assert_eq!(profiles.all().len(), 2);
+
+        let work = profiles.select(None)?;
+        assert_eq!((work.profile_name(), work.username.as_str(), work.client_id.as_str()), ("work", "work_user", "id"));
+        let personal = profiles.select(Some("personal"))?;
+        assert_eq!((personal.username.as_str(), personal.client_id.as_str()), ("me", "other"));
+        assert!(profiles.select(Some("missing")).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn several_profiles_need_a_choice() -> Result<(), Box<dyn Error>> {
+        let contents = "user_agent = \"ua\"\nclient_id = \"id\"\nclient_secret = \"s\"\n[profiles.a]\n[profiles.b]\n";
+        let profiles = parse_config(contents, Path::new("config.toml"), false)?;
+        let err = profiles.select(None).expect_err("Expected ambiguous profile error");
+        assert!(err.to_string().contains("a, b"));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn group_or_world_readable_files_are_flagged() {
+        assert!(permission_problems(0o100600, 1000, 1000).is_empty());
+        assert_eq!(permission_problems(0o100640, 1000, 1000).len(), 1);
+        assert!(permission_problems(0o100644, 1000, 1000)[0].contains("mode 0644"));
+        assert!(permission_problems(0o100600, 0, 1000)[0].contains("owned by uid 0"));
+    }
+
     #[test]
     fn load_config_reports_checked_paths() {
         let env_paths = EnvironmentPaths {
@@ -231,7 +504,7 @@ mod tests {
             current_dir: Some(PathBuf::from("/work/project")),
         };
 
-        let err = load_config_with_env("missing-config.toml", false, env_paths)
+        let err = load_config_with_env("missing-config.toml", false, false, env_paths)
             .expect_err("Expected missing config error");
         let io_err = err.downcast::<io::Error>().unwrap();
 
@@ -0,0 +1,683 @@
+// The `--where` filter expression language.
+//
+//   subreddit in (rust, golang) and score < 2 and age > 1y and not body ~ "keep"
+//
+// Conditions compare a field of the item with a value and are combined with `and`, `or`,
+// `not` and parentheses (`not` binds tightest, then `and`, then `or`). Text fields support
+// `=`, `!=` and `in (...)` (case-insensitive) and `~`/`!~` (case-insensitive regular
+// expression); numbers and `age` support `=`, `!=`, `<`, `<=`, `>`, `>=`. `age` takes a
+// duration (`30d`, `1y`, `"2 weeks"`) or a date (`2024-01-15`): `age > 1y` keeps items older
+// than a year. The flags `edited`, `stickied`, `locked`, `nsfw`, `spoiler` and `archived` are
+// true or false and can stand alone (`archived and not edited`). The command-line filter flags
+// are compiled into the same `Expr` tree.
+use crate::models::UnifiedItem;
+use crate::utils::{format_timestamp_iso, parse_age_to_timestamp};
+use regex::{Regex, RegexBuilder};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Field {
+    Subreddit,
+    Type,
+    Source,
+    Title,
+    Body,
+    Author,
+    Recipient,
+    Id,
+    Permalink,
+    Score,
+    Comments,
+    Age,
+    Edited,
+    Stickied,
+    Locked,
+    Nsfw,
+    Spoiler,
+    Archived,
+    Distinguished,
+}
+
+const FIELD_NAMES: &str = "subreddit, type, source, title, body, author, recipient, id, permalink, score, comments, age, \
+     edited, stickied, locked, nsfw, spoiler, archived, distinguished";
+
+/// What an item holds for a field.
+enum FieldValue<'a> {
+    Text(&'a str),
+    Number(f64),
+    Bool(bool),
+}
+
+impl Field {
+    fn from_name(name: &str) -> Option<Field> {
+        match name.to_lowercase().as_str() {
+            "subreddit" | "sub" => Some(Field::Subreddit),
+            "type" => Some(Field::Type),
+            "source" => Some(Field::Source),
+            "title" => Some(Field::Title),
+            "body" | "content" => Some(Field::Body),
+            "author" => Some(Field::Author),
+            "recipient" => Some(Field::Recipient),
+            "id" => Some(Field::Id),
+            "permalink" => Some(Field::Permalink),
+            "score" | "upvotes" => Some(Field::Score),
+            "comments" | "num_comments" => Some(Field::Comments),
+            "age" => Some(Field::Age),
+            "edited" => Some(Field::Edited),
+            "stickied" => Some(Field::Stickied),
+            "locked" => Some(Field::Locked),
+            "nsfw" | "over_18" => Some(Field::Nsfw),
+            "spoiler" => Some(Field::Spoiler),
+            "archived" => Some(Field::Archived),
+            "distinguished" => Some(Field::Distinguished),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Field::Subreddit => "subreddit",
+            Field::Type => "type",
+            Field::Source => "source",
+            Field::Title => "title",
+            Field::Body => "body",
+            Field::Author => "author",
+            Field::Recipient => "recipient",
+            Field::Id => "id",
+            Field::Permalink => "permalink",
+            Field::Score => "score",
+            Field::Comments => "comments",
+            Field::Age => "age",
+            Field::Edited => "edited",
+            Field::Stickied => "stickied",
+            Field::Locked => "locked",
+            Field::Nsfw => "nsfw",
+            Field::Spoiler => "spoiler",
+            Field::Archived => "archived",
+            Field::Distinguished => "distinguished",
+        }
+    }
+
+    fn is_text(self) -> bool {
+        !self.is_flag() && !matches!(self, Field::Score | Field::Comments | Field::Age)
+    }
+
+    fn is_flag(self) -> bool {
+        matches!(
+            self,
+            Field::Edited | Field::Stickied | Field::Locked | Field::Nsfw | Field::Spoiler | Field::Archived
+        )
+    }
+
+    /// The item's value; for `age` this is the creation timestamp.
+    fn value(self, item: &UnifiedItem) -> FieldValue<'_> {
+        match self {
+            Field::Subreddit => FieldValue::Text(&item.subreddit),
+            Field::Type => FieldValue::Text(&item.item_type),
+            Field::Source => FieldValue::Text(&item.source),
+            Field::Title => FieldValue::Text(&item.title),
+            Field::Body => FieldValue::Text(&item.content),
+            Field::Author => FieldValue::Text(&item.author),
+            Field::Recipient => FieldValue::Text(&item.recipient),
+            Field::Id => FieldValue::Text(&item.id),
+            Field::Permalink => FieldValue::Text(&item.permalink),
+            Field::Score => FieldValue::Number(item.upvotes as f64),
+            Field::Comments => FieldValue::Number(item.num_comments as f64),
+            Field::Age => FieldValue::Number(item.created_utc),
+            Field::Edited => FieldValue::Bool(item.flags.edited),
+            Field::Stickied => FieldValue::Bool(item.flags.stickied),
+            Field::Locked => FieldValue::Bool(item.flags.locked),
+            Field::Nsfw => FieldValue::Bool(item.flags.over_18),
+            Field::Spoiler => FieldValue::Bool(item.flags.spoiler),
+            Field::Archived => FieldValue::Bool(item.flags.archived),
+            Field::Distinguished => FieldValue::Text(&item.flags.distinguished),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CompareOp {
+    fn holds(self, left: f64, right: f64) -> bool {
+        match self {
+            CompareOp::Eq => left == right,
+            CompareOp::Ne => left != right,
+            CompareOp::Lt => left < right,
+            CompareOp::Le => left <= right,
+            CompareOp::Gt => left > right,
+            CompareOp::Ge => left >= right,
+        }
+    }
+
+    fn symbol(self) -> &'static str {
+        match self {
+            CompareOp::Eq => "=",
+            CompareOp::Ne => "!=",
+            CompareOp::Lt => "<",
+            CompareOp::Le => "<=",
+            CompareOp::Gt => ">",
+            CompareOp::Ge => ">=",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    /// A number, or for `age` the timestamp the duration or date resolved to.
+    Number(f64),
+    Bool(bool),
+}
+
+/// A parsed filter expression.
+#[derive(Debug, Clone)]
+pub enum Expr {
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Compare(Field, CompareOp, Value),
+    In(Field, Vec<Value>),
+    Matches(Field, Regex),
+}
+
+impl Expr {
+    /// Case-insensitive substring match on a text field.
+    pub fn contains(field: Field, text: &str) -> Expr {
+        let regex = RegexBuilder::new(&regex::escape(text))
+            .case_insensitive(true)
+            .build()
+            .expect("an escaped literal is a valid regex");
+        Expr::Matches(field, regex)
+    }
+
+    pub fn matches(&self, item: &UnifiedItem) -> bool {
+        match self {
+            Expr::And(left, right) => left.matches(item) && right.matches(item),
+            Expr::Or(left, right) => left.matches(item) || right.matches(item),
+            Expr::Not(inner) => !inner.matches(item),
+            Expr::Compare(field, op, value) => compare(*field, *op, value, item),
+            Expr::In(field, values) => values.iter().any(|value| compare(*field, CompareOp::Eq, value, item)),
+            Expr::Matches(field, regex) => match field.value(item) {
+                FieldValue::Text(text) => regex.is_match(text),
+                FieldValue::Number(_) | FieldValue::Bool(_) => false,
+            },
+        }
+    }
+}
+
+fn compare(field: Field, op: CompareOp, value: &Value, item: &UnifiedItem) -> bool {
+    match (field.value(item), value) {
+        (FieldValue::Text(text), Value::Text(expected)) => {
+            let equal = text.to_lowercase() == expected.to_lowercase();
+            match op {
+                CompareOp::Eq => equal,
+                CompareOp::Ne => !equal,
+                _ => false,
+            }
+        }
+        // `age > cutoff` means created before the cutoff timestamp
+        (FieldValue::Number(created_utc), Value::Number(cutoff)) if field == Field::Age => op.holds(*cutoff, created_utc),
+        (FieldValue::Number(number), Value::Number(expected)) => op.holds(number, *expected),
+        (FieldValue::Bool(flag), Value::Bool(expected)) => match op {
+            CompareOp::Eq => flag == *expected,
+            CompareOp::Ne => flag != *expected,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn fmt_value(f: &mut fmt::Formatter<'_>, field: Field, value: &Value) -> fmt::Result {
+    match value {
+        Value::Text(text) => write!(f, "\"{}\"", text.replace('"', "\\\"")),
+        Value::Number(timestamp) if field == Field::Age => write!(f, "{}", format_timestamp_iso(*timestamp)),
+        Value::Number(number) => write!(f, "{}", number),
+        Value::Bool(flag) => write!(f, "{}", flag),
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::And(left, right) => write!(f, "({} and {})", left, right),
+            Expr::Or(left, right) => write!(f, "({} or {})", left, right),
+            Expr::Not(inner) => write!(f, "not {}", inner),
+            Expr::Compare(field, op, value) => {
+                write!(f, "{} {} ", field.name(), op.symbol())?;
+                fmt_value(f, *field, value)
+            }
+            Expr::In(field, values) => {
+                write!(f, "{} in (", field.name())?;
+                for (i, value) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    fmt_value(f, *field, value)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Matches(field, regex) => write!(f, "{} ~ \"{}\"", field.name(), regex.as_str().replace('"', "\\\"")),
+        }
+    }
+}
+
+/// A syntax error, pointing at the offending position of the expression.
+#[derive(Debug)]
+pub struct ParseError {
+    message: String,
+    input: String,
+    position: usize,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let column = self.input[..self.position].chars().count();
+        write!(f, "{}\n  {}\n  {}^", self.message, self.input, " ".repeat(column))
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    LParen,
+    RParen,
+    Comma,
+    Compare(CompareOp),
+    Match { negated: bool },
+    Word(String),
+    Quoted(String),
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match &self.kind {
+            TokenKind::LParen => "`(`".to_string(),
+            TokenKind::RParen => "`)`".to_string(),
+            TokenKind::Comma => "`,`".to_string(),
+            TokenKind::Compare(op) => format!("`{}`", op.symbol()),
+            TokenKind::Match { negated: false } => "`~`".to_string(),
+            TokenKind::Match { negated: true } => "`!~`".to_string(),
+            TokenKind::Word(word) => format!("`{}`", word),
+            TokenKind::Quoted(text) => format!("\"{}\"", text),
+        }
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(&self.kind, TokenKind::Word(word) if word.eq_ignore_ascii_case(keyword))
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !"(),=!<>~\"'".contains(c)
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let error = |message: String, position: usize| ParseError { message, input: input.to_string(), position };
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(start, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        chars.next();
+        let next = chars.peek().map(|&(_, next)| next);
+        let kind = match c {
+            '(' => TokenKind::LParen,
+            ')' => TokenKind::RParen,
+            ',' => TokenKind::Comma,
+            '~' => TokenKind::Match { negated: false },
+            '=' => {
+                if next == Some('=') {
+                    chars.next();
+                }
+                TokenKind::Compare(CompareOp::Eq)
+            }
+            '!' => match next {
+                Some('=') => {
+                    chars.next();
+                    TokenKind::Compare(CompareOp::Ne)
+                }
+                Some('~') => {
+                    chars.next();
+                    TokenKind::Match { negated: true }
+                }
+                _ => return Err(error("expected `!=` or `!~`; use `not` to negate a condition".to_string(), start)),
+            },
+            '<' | '>' => {
+                let or_equal = next == Some('=');
+                if or_equal {
+                    chars.next();
+                }
+                TokenKind::Compare(match (c, or_equal) {
+                    ('<', false) => CompareOp::Lt,
+                    ('<', true) => CompareOp::Le,
+                    (_, false) => CompareOp::Gt,
+                    (_, true) => CompareOp::Ge,
+                })
+            }
+            '"' | '\'' => {
+                // Only the quote itself can be escaped, so regex escapes like \b pass through
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '\\')) if chars.peek().map(|&(_, next)| next) == Some(c) => {
+                            chars.next();
+                            text.push(c);
+                        }
+                        Some((_, ch)) if ch == c => break,
+                        Some((_, ch)) => text.push(ch),
+                        None => return Err(error("unterminated string".to_string(), start)),
+                    }
+                }
+                TokenKind::Quoted(text)
+            }
+            _ => {
+                let mut word = c.to_string();
+                while let Some(&(_, ch)) = chars.peek() {
+                    if !is_word_char(ch) {
+                        break;
+                    }
+                    word.push(ch);
+                    chars.next();
+                }
+                TokenKind::Word(word)
+            }
+        };
+        tokens.push(Token { kind, start });
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: String) -> ParseError {
+        let position = self.tokens.get(self.pos).map_or(self.input.len(), |token| token.start);
+        ParseError { message, input: self.input.to_string(), position }
+    }
+
+    /// "found `x`" for the current token, or "the expression ended".
+    fn found(&self) -> String {
+        match self.tokens.get(self.pos) {
+            Some(token) => format!("found {}", token.describe()),
+            None => "the expression ended".to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next_is_keyword(&self, keyword: &str) -> bool {
+        self.peek().is_some_and(|token| token.is_keyword(keyword))
+    }
+
+    fn expect(&mut self, kind: TokenKind, description: &str) -> Result<(), ParseError> {
+        if self.peek().map(|token| &token.kind) == Some(&kind) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(self.error(format!("expected {}, {}", description, self.found())))
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_and()?;
+        while self.next_is_keyword("or") {
+            self.pos += 1;
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_and(&mut self) -> Result<Expr, ParseError> {
+        let mut expr = self.parse_unary()?;
+        while self.next_is_keyword("and") {
+            self.pos += 1;
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, ParseError> {
+        if self.next_is_keyword("not") {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, ParseError> {
+        match self.peek().map(|token| &token.kind) {
+            Some(TokenKind::LParen) => {
+                self.pos += 1;
+                let expr = self.parse_or()?;
+                self.expect(TokenKind::RParen, "`)`")?;
+                Ok(expr)
+            }
+            Some(TokenKind::Word(name)) if !["and", "or", "in"].iter().any(|k| name.eq_ignore_ascii_case(k)) => {
+                let field = Field::from_name(name)
+                    .ok_or_else(|| self.error(format!("unknown field `{}`; available fields: {}", name, FIELD_NAMES)))?;
+                self.pos += 1;
+                self.parse_condition(field)
+            }
+            _ => Err(self.error(format!("expected a field name, `not` or `(`, {}", self.found()))),
+        }
+    }
+
+    fn parse_condition(&mut self, field: Field) -> Result<Expr, ParseError> {
+        if self.next_is_keyword("not") && self.tokens.get(self.pos + 1).is_some_and(|token| token.is_keyword("in")) {
+            self.pos += 1;
+            return Ok(Expr::Not(Box::new(self.parse_condition(field)?)));
+        }
+        if self.next_is_keyword("in") {
+            if field == Field::Age || field.is_flag() {
+                return Err(self.error(format!("`{}` cannot be used with `in`", field.name())));
+            }
+            self.pos += 1;
+            self.expect(TokenKind::LParen, "`(` after `in`")?;
+            let mut values = vec![self.parse_value(field)?];
+            while self.peek().map(|token| &token.kind) == Some(&TokenKind::Comma) {
+                self.pos += 1;
+                values.push(self.parse_value(field)?);
+            }
+            self.expect(TokenKind::RParen, "`,` or `)`")?;
+            return Ok(Expr::In(field, values));
+        }
+
+        match self.peek().map(|token| token.kind.clone()) {
+            Some(TokenKind::Compare(op)) => {
+                if field.is_flag() && !matches!(op, CompareOp::Eq | CompareOp::Ne) {
+                    return Err(self.error(format!(
+                        "`{}` is true or false; use it on its own, with `not`, or with = and !=",
+                        field.name()
+                    )));
+                }
+                if field.is_text() && !matches!(op, CompareOp::Eq | CompareOp::Ne) {
+                    return Err(self.error(format!(
+                        "`{}` cannot compare text; `{}` supports =, !=, ~, !~ and in",
+                        op.symbol(),
+                        field.name()
+                    )));
+                }
+                self.pos += 1;
+                Ok(Expr::Compare(field, op, self.parse_value(field)?))
+            }
+            Some(TokenKind::Match { negated }) => {
+                if !field.is_text() {
+                    return Err(self.error(format!("`~` needs a text field, but `{}` is not text", field.name())));
+                }
+                self.pos += 1;
+                let pattern = self.parse_text()?;
+                let regex = RegexBuilder::new(&pattern).case_insensitive(true).build().map_err(|e| {
+                    self.pos -= 1;
+                    self.error(format!("invalid regular expression: {}", e))
+                })?;
+                let expr = Expr::Matches(field, regex);
+                Ok(if negated { Expr::Not(Box::new(expr)) } else { expr })
+            }
+            // A flag on its own means "is set"
+            _ if field.is_flag() => Ok(Expr::Compare(field, CompareOp::Eq, Value::Bool(true))),
+            _ => Err(self.error(format!(
+                "expected an operator (=, !=, <, <=, >, >=, ~, !~) or `in` after `{}`, {}",
+                field.name(),
+                self.found()
+            ))),
+        }
+    }
+
+    fn parse_text(&mut self) -> Result<String, ParseError> {
+        match self.peek().map(|token| &token.kind) {
+            Some(TokenKind::Word(text)) | Some(TokenKind::Quoted(text)) => {
+                let text = text.clone();
+                self.pos += 1;
+                Ok(text)
+            }
+            _ => Err(self.error(format!("expected a value, {}", self.found()))),
+        }
+    }
+
+    fn parse_value(&mut self, field: Field) -> Result<Value, ParseError> {
+        let text = self.parse_text()?;
+        let value = match field {
+            Field::Age => parse_age_to_timestamp(&text)
+                .map(Value::Number)
+                .map_err(|_| format!("`age` needs a duration like 30d or 1y, or a date like 2024-01-15, found `{}`", text)),
+            _ if field.is_flag() => match text.to_lowercase().as_str() {
+                "true" | "yes" | "1" => Ok(Value::Bool(true)),
+                "false" | "no" | "0" => Ok(Value::Bool(false)),
+                _ => Err(format!("`{}` needs true or false, found `{}`", field.name(), text)),
+            },
+            _ if field.is_text() => Ok(Value::Text(text)),
+            _ => text
+                .parse::<f64>()
+                .map(Value::Number)
+                .map_err(|_| format!("`{}` needs a number, found `{}`", field.name(), text)),
+        };
+        value.map_err(|message| {
+            self.pos -= 1;
+            self.error(message)
+        })
+    }
+}
+
+/// Parse a `--where` expression.
+pub fn parse(input: &str) -> Result<Expr, ParseError> {
+    let mut parser = Parser { input, tokens: tokenize(input)?, pos: 0 };
+    let expr = parser.parse_or()?;
+    if let Some(token) = parser.peek() {
+        return Err(parser.error(format!("unexpected {}; join conditions with `and` or `or`", token.describe())));
+    }
+    Ok(expr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(item_type: &str, subreddit: &str, upvotes: i32, content: &str) -> UnifiedItem {
+        let mut item = UnifiedItem::from_fullname(if item_type == "Post" { "t3_abc" } else { "t1_abc" });
+        item.subreddit = subreddit.to_string();
+        item.upvotes = upvotes;
+        item.content = content.to_string();
+        item.created_utc = 1_000_000_000.0;
+        item
+    }
+
+    #[test]
+    fn evaluates_combined_conditions() -> Result<(), Box<dyn Error>> {
+        let expr = parse(r#"subreddit in (rust, GoLang) and score < 2 and age > 1y and not body ~ "keep""#)?;
+
+        assert!(expr.matches(&item("Comment", "golang", 1, "old stuff")));
+        assert!(!expr.matches(&item("Comment", "golang", 1, "please KEEP this")));
+        assert!(!expr.matches(&item("Comment", "golang", 2, "old stuff")));
+        assert!(!expr.matches(&item("Comment", "python", 1, "old stuff")));
+        Ok(())
+    }
+
+    #[test]
+    fn precedence_and_parentheses() -> Result<(), Box<dyn Error>> {
+        let expr = parse("type = post or score >= 10 and subreddit != rust")?;
+        assert_eq!(expr.to_string(), r#"(type = "post" or (score >= 10 and subreddit != "rust"))"#);
+        assert!(expr.matches(&item("Post", "rust", 0, "")));
+
+        let expr = parse("(type = post or score >= 10) and subreddit not in (rust)")?;
+        assert!(!expr.matches(&item("Post", "rust", 0, "")));
+        assert!(expr.matches(&item("Comment", "golang", 10, "")));
+        Ok(())
+    }
+
+    #[test]
+    fn age_accepts_dates_and_regexes_keep_backslashes() -> Result<(), Box<dyn Error>> {
+        // The test items were created on 2001-09-09
+        let expr = parse(r#"age > 2001-09-10 and body ~ "\bacme\b""#)?;
+        assert!(expr.matches(&item("Comment", "rust", 0, "I worked at ACME")));
+        assert!(!expr.matches(&item("Comment", "rust", 0, "acmes")));
+
+        let expr = parse("age > 2001-09-09 and body ~ '\\bacme\\b'")?;
+        assert!(!expr.matches(&item("Comment", "rust", 0, "I worked at ACME")));
+        Ok(())
+    }
+
+    #[test]
+    fn flags_stand_alone_or_compare_to_booleans() -> Result<(), Box<dyn Error>> {
+        let mut archived = item("Post", "rust", 1, "");
+        archived.flags.archived = true;
+        archived.flags.distinguished = "moderator".to_string();
+
+        let expr = parse("archived and not edited")?;
+        assert_eq!(expr.to_string(), "(archived = true and not edited = true)");
+        assert!(expr.matches(&archived));
+        assert!(!expr.matches(&item("Post", "rust", 1, "")));
+        assert!(parse("nsfw = false and distinguished = moderator")?.matches(&archived));
+        assert!(parse("locked > 1").unwrap_err().to_string().starts_with("`locked` is true or false"));
+        assert!(parse("spoiler = maybe").unwrap_err().to_string().starts_with("`spoiler` needs true or false"));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_errors_point_at_the_problem() {
+        let error = parse("subreddit = rust and score <").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "expected a value, the expression ended\n  subreddit = rust and score <\n                              ^"
+        );
+
+        let error = parse("title < 5").unwrap_err();
+        assert!(error.to_string().starts_with("`<` cannot compare text"));
+
+        let error = parse("karma > 5").unwrap_err();
+        assert!(error.to_string().starts_with("unknown field `karma`"));
+
+        let error = parse("score > lots").unwrap_err();
+        assert!(error.to_string().starts_with("`score` needs a number, found `lots`\n  score > lots\n          ^"));
+
+        let error = parse("score > 1 score < 5").unwrap_err();
+        assert!(error.to_string().starts_with("unexpected `score`; join conditions with `and` or `or`"));
+
+        let error = parse("body ~ \"(unclosed\"").unwrap_err();
+        assert!(error.to_string().starts_with("invalid regular expression"));
+    }
+}
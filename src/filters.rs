@@ -0,0 +1,335 @@
+use crate::cli::ItemFlag;
+use crate::filter_expr::{CompareOp, Expr, Field, Value};
+use crate::models::UnifiedItem;
+use regex::RegexBuilder;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+
+/// Item selection built from the command-line filter flags and `--where`.
+///
+/// Every flag is compiled into a filter expression, so posts and comments from any listing
+/// (or any sort order) go through exactly the same engine as `--where`. An item is kept when
+/// all conditions match.
+#[derive(Debug, Clone, Default)]
+pub struct ItemFilters {
+    conditions: Vec<Expr>,
+}
+
+impl ItemFilters {
+    /// `min_age_timestamp`: items must be created at or before it (older than `--min-age`).
+    /// `max_age_timestamp`: items must be created at or after it (newer than `--max-age`).
+    pub fn new(
+        subreddit: Option<&String>,
+        exclude_subreddit: Option<&String>,
+        min_score: Option<i32>,
+        max_score: Option<i32>,
+        min_age_timestamp: Option<f64>,
+        max_age_timestamp: Option<f64>,
+        post_title: Option<&String>,
+    ) -> Self {
+        let mut conditions = Vec::new();
+        if let Some(list) = subreddit {
+            conditions.push(Expr::In(Field::Subreddit, parse_subreddit_list(list)));
+        }
+        if let Some(list) = exclude_subreddit {
+            conditions.push(Expr::Not(Box::new(Expr::In(Field::Subreddit, parse_subreddit_list(list)))));
+        }
+        if let Some(score) = min_score {
+            conditions.push(Expr::Compare(Field::Score, CompareOp::Ge, Value::Number(score as f64)));
+        }
+        if let Some(score) = max_score {
+            conditions.push(Expr::Compare(Field::Score, CompareOp::Lt, Value::Number(score as f64)));
+        }
+        if let Some(timestamp) = min_age_timestamp {
+            conditions.push(Expr::Compare(Field::Age, CompareOp::Ge, Value::Number(timestamp)));
+        }
+        if let Some(timestamp) = max_age_timestamp {
+            conditions.push(Expr::Compare(Field::Age, CompareOp::Le, Value::Number(timestamp)));
+        }
+        // Post title filtering only applies to comments (title holds the link title)
+        if let Some(title) = post_title {
+            conditions.push(Expr::Or(
+                Box::new(Expr::Compare(Field::Type, CompareOp::Ne, Value::Text("Comment".to_string()))),
+                Box::new(Expr::contains(Field::Title, title)),
+            ));
+        }
+        Self { conditions }
+    }
+
+    /// Add a parsed `--where` expression.
+    pub fn with_expression(mut self, expr: Expr) -> Self {
+        self.conditions.push(expr);
+        self
+    }
+
+    pub fn matches(&self, item: &UnifiedItem) -> bool {
+        self.conditions.iter().all(|condition| condition.matches(item))
+    }
+}
+
+impl fmt::Display for ItemFilters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.conditions.is_empty() {
+            return write!(f, "(none)");
+        }
+        for (i, condition) in self.conditions.iter().enumerate() {
+            if i > 0 {
+                write!(f, " and ")?;
+            }
+            write!(f, "{}", condition)?;
+        }
+        Ok(())
+    }
+}
+
+/// The regex and keyword filter flags, matched against what an item says.
+#[derive(Debug, Default)]
+pub struct ContentFilters {
+    pub content_regex: Option<String>,
+    pub exclude_content_regex: Option<String>,
+    pub title_regex: Option<String>,
+    pub exclude_title_regex: Option<String>,
+    pub keyword_file: Option<String>,
+    pub exclude_keyword_file: Option<String>,
+    pub case_sensitive: bool,
+    /// Keywords only match whole words.
+    pub whole_word: bool,
+}
+
+impl ContentFilters {
+    /// Compile the flags into filter conditions, reading the keyword files.
+    pub fn conditions(&self) -> Result<Vec<Expr>, Box<dyn Error>> {
+        let mut conditions = Vec::new();
+        let regexes = [
+            ("--content-regex", &self.content_regex, Field::Body, false),
+            ("--exclude-content-regex", &self.exclude_content_regex, Field::Body, true),
+            ("--title-regex", &self.title_regex, Field::Title, false),
+            ("--exclude-title-regex", &self.exclude_title_regex, Field::Title, true),
+        ];
+        for (flag, pattern, field, exclude) in regexes {
+            if let Some(pattern) = pattern {
+                let regex = RegexBuilder::new(pattern)
+                    .case_insensitive(!self.case_sensitive)
+                    .build()
+                    .map_err(|e| format!("Invalid {}: {}", flag, e))?;
+                conditions.push(negate_if(Expr::Matches(field, regex), exclude));
+            }
+        }
+        let keyword_files = [(&self.keyword_file, false), (&self.exclude_keyword_file, true)];
+        for (path, exclude) in keyword_files {
+            if let Some(path) = path {
+                let terms = load_keyword_file(path)?;
+                conditions.push(negate_if(self.keyword_condition(&terms)?, exclude));
+            }
+        }
+        Ok(conditions)
+    }
+
+    /// Any term in the body, or in the title of posts and messages (a comment's title is
+    /// the title of the post it replies to).
+    fn keyword_condition(&self, terms: &[String]) -> Result<Expr, Box<dyn Error>> {
+        let alternatives: Vec<String> = terms
+            .iter()
+            .map(|term| {
+                let escaped = regex::escape(term);
+                if !self.whole_word {
+                    return escaped;
+                }
+                // \b only applies next to word characters, so terms like "C++" still match
+                let is_word_char = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+                let start = if is_word_char(term.chars().next()) { "\\b" } else { "" };
+                let end = if is_word_char(term.chars().last()) { "\\b" } else { "" };
+                format!("{}{}{}", start, escaped, end)
+            })
+            .collect();
+        let regex = RegexBuilder::new(&alternatives.join("|"))
+            .case_insensitive(!self.case_sensitive)
+            .build()?;
+        Ok(Expr::Or(
+            Box::new(Expr::Matches(Field::Body, regex.clone())),
+            Box::new(Expr::And(
+                Box::new(Expr::Compare(Field::Type, CompareOp::Ne, Value::Text("Comment".to_string()))),
+                Box::new(Expr::Matches(Field::Title, regex)),
+            )),
+        ))
+    }
+}
+
+fn negate_if(expr: Expr, negate: bool) -> Expr {
+    if negate { Expr::Not(Box::new(expr)) } else { expr }
+}
+
+/// Read one term per line, skipping blank lines and `#` comments.
+fn load_keyword_file(path: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("Failed to read keyword file '{}': {}", path, e))?;
+    let terms: Vec<String> = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect();
+    if terms.is_empty() {
+        return Err(format!("Keyword file '{}' contains no terms", path).into());
+    }
+    Ok(terms)
+}
+
+/// Condition that `flag` is set on an item.
+pub fn flag_condition(flag: ItemFlag) -> Expr {
+    let field = match flag {
+        ItemFlag::Edited => Field::Edited,
+        ItemFlag::Stickied => Field::Stickied,
+        ItemFlag::Locked => Field::Locked,
+        ItemFlag::Nsfw => Field::Nsfw,
+        ItemFlag::Spoiler => Field::Spoiler,
+        ItemFlag::Archived => Field::Archived,
+        ItemFlag::Distinguished => {
+            return Expr::Compare(Field::Distinguished, CompareOp::Ne, Value::Text(String::new()));
+        }
+    };
+    Expr::Compare(field, CompareOp::Eq, Value::Bool(true))
+}
+
+/// Parse a comma-separated subreddit list into lowercase names, dropping empty entries.
+fn parse_subreddit_list(list: &str) -> Vec<Value> {
+    list.split(',')
+        .map(|sr| sr.trim().to_lowercase())
+        .filter(|sr| !sr.is_empty())
+        .map(Value::Text)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(item_type: &str, subreddit: &str, upvotes: i32, created_utc: f64) -> UnifiedItem {
+        let mut item = UnifiedItem::from_fullname(if item_type == "Post" { "t3_abc" } else { "t1_abc" });
+        item.subreddit = subreddit.to_string();
+        item.upvotes = upvotes;
+        item.created_utc = created_utc;
+        item
+    }
+
+    #[test]
+    fn subreddit_lists_are_case_insensitive() {
+        let include = "Rust, golang".to_string();
+        let exclude = "golang".to_string();
+        let filters = ItemFilters::new(Some(&include), Some(&exclude), None, None, None, None, None);
+
+        assert!(filters.matches(&item("Post", "rust", 1, 0.0)));
+        assert!(!filters.matches(&item("Post", "GoLang", 1, 0.0)));
+        assert!(!filters.matches(&item("Post", "python", 1, 0.0)));
+    }
+
+    #[test]
+    fn score_range_is_inclusive_min_exclusive_max() {
+        let filters = ItemFilters::new(None, None, Some(5), Some(10), None, None, None);
+
+        assert!(!filters.matches(&item("Comment", "rust", 4, 0.0)));
+        assert!(filters.matches(&item("Comment", "rust", 5, 0.0)));
+        assert!(!filters.matches(&item("Comment", "rust", 10, 0.0)));
+    }
+
+    #[test]
+    fn post_title_only_restricts_comments() {
+        let title = "other".to_string();
+        let filters = ItemFilters::new(None, None, None, None, None, None, Some(&title));
+
+        assert!(filters.matches(&item("Post", "rust", 1, 0.0)));
+        assert!(!filters.matches(&item("Comment", "rust", 1, 0.0)));
+    }
+
+    #[test]
+    fn age_bounds_select_window() {
+        let filters = ItemFilters::new(None, None, None, None, Some(200.0), Some(100.0), None);
+
+        assert!(filters.matches(&item("Post", "rust", 1, 150.0)));
+        assert!(!filters.matches(&item("Post", "rust", 1, 250.0)));
+        assert!(!filters.matches(&item("Post", "rust", 1, 50.0)));
+    }
+
+    #[test]
+    fn flags_and_where_expression_must_all_match() -> Result<(), Box<dyn std::error::Error>> {
+        let include = "rust".to_string();
+        let filters = ItemFilters::new(Some(&include), None, Some(1), None, None, None, None)
+            .with_expression(crate::filter_expr::parse("score < 5 or type = post")?);
+
+        assert_eq!(filters.to_string(), r#"subreddit in ("rust") and score >= 1 and (score < 5 or type = "post")"#);
+        assert!(filters.matches(&item("Comment", "rust", 3, 0.0)));
+        assert!(!filters.matches(&item("Comment", "rust", 7, 0.0)));
+        assert!(filters.matches(&item("Post", "rust", 7, 0.0)));
+        assert!(!filters.matches(&item("Post", "golang", 3, 0.0)));
+        Ok(())
+    }
+
+    #[test]
+    fn keyword_file_matches_body_and_own_titles() -> Result<(), Box<dyn std::error::Error>> {
+        let temp = tempfile::tempdir()?;
+        let path = temp.path().join("keywords.txt");
+        fs::write(&path, "# former employers\nACME\n\nC++\n")?;
+        let mut content = ContentFilters {
+            keyword_file: Some(path.to_string_lossy().into_owned()),
+            whole_word: true,
+            ..Default::default()
+        };
+        let filters = content.conditions()?.into_iter().fold(ItemFilters::default(), ItemFilters::with_expression);
+
+        let mut comment = item("Comment", "rust", 1, 0.0);
+        comment.content = "Back when I was at Acme, we wrote C++".to_string();
+        assert!(filters.matches(&comment));
+        comment.content = "acmeville".to_string();
+        assert!(!filters.matches(&comment));
+        // A comment's title belongs to someone else's post
+        comment.title = "ACME layoffs".to_string();
+        assert!(!filters.matches(&comment));
+        let mut post = item("Post", "rust", 1, 0.0);
+        post.title = "ACME layoffs".to_string();
+        assert!(filters.matches(&post));
+
+        content.case_sensitive = true;
+        content.keyword_file = None;
+        content.exclude_keyword_file = Some(path.to_string_lossy().into_owned());
+        let filters = content.conditions()?.into_iter().fold(ItemFilters::default(), ItemFilters::with_expression);
+        post.title = "acme layoffs".to_string();
+        assert!(filters.matches(&post));
+        Ok(())
+    }
+
+    #[test]
+    fn regex_flags_and_their_negations() -> Result<(), Box<dyn std::error::Error>> {
+        let content = ContentFilters {
+            content_regex: Some("^edit".to_string()),
+            exclude_title_regex: Some("megathread".to_string()),
+            ..Default::default()
+        };
+        let filters = content.conditions()?.into_iter().fold(ItemFilters::default(), ItemFilters::with_expression);
+
+        let mut comment = item("Comment", "rust", 1, 0.0);
+        comment.content = "EDIT: fixed".to_string();
+        assert!(filters.matches(&comment));
+        comment.title = "Weekly Megathread".to_string();
+        assert!(!filters.matches(&comment));
+
+        let invalid = ContentFilters { title_regex: Some("(".to_string()), ..Default::default() };
+        assert!(invalid.conditions().unwrap_err().to_string().starts_with("Invalid --title-regex"));
+        Ok(())
+    }
+
+    #[test]
+    fn flag_conditions() {
+        let mut stickied = item("Post", "rust", 1, 0.0);
+        stickied.flags.stickied = true;
+        stickied.flags.distinguished = "moderator".to_string();
+        let plain = item("Post", "rust", 1, 0.0);
+
+        let filters = ItemFilters::default()
+            .with_expression(flag_condition(ItemFlag::Distinguished))
+            .with_expression(Expr::Not(Box::new(flag_condition(ItemFlag::Archived))));
+        assert!(filters.matches(&stickied));
+        assert!(!filters.matches(&plain));
+        assert!(flag_condition(ItemFlag::Stickied).matches(&stickied));
+        assert!(!flag_condition(ItemFlag::Stickied).matches(&plain));
+    }
+}
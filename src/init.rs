@@ -0,0 +1,244 @@
+// `bydit init`: first-time setup wizard.
+//
+// Asks for the app credentials and account, checks them by logging in, and writes
+// `config.toml` to the XDG config directory (mode 0600), the second place Bydit searches.
+use crate::auth;
+use crate::config::{candidate_paths, config_dir, Config};
+use crate::reddit_ops::connect_reddit;
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+const CONFIG_FILE: &str = "config.toml";
+
+/// Answers collected by the wizard.
+#[derive(Debug, PartialEq)]
+struct InitAnswers {
+    user_agent: String,
+    client_id: String,
+    client_secret: String,
+    username: String,
+    /// Empty when the account is authorized in the browser instead.
+    password: String,
+    /// Whether the password login needs a two-factor code.
+    two_factor: bool,
+    /// Base32 secret the codes are generated from; empty to type codes in (`--otp-prompt`).
+    totp_secret: String,
+}
+
+impl InitAnswers {
+    fn to_toml(&self) -> Result<String, Box<dyn Error>> {
+        let mut table = toml::Table::new();
+        table.insert("user_agent".to_string(), self.user_agent.clone().into());
+        table.insert("client_id".to_string(), self.client_id.clone().into());
+        table.insert("client_secret".to_string(), self.client_secret.clone().into());
+        table.insert("username".to_string(), self.username.clone().into());
+        if !self.password.is_empty() {
+            table.insert("password".to_string(), self.password.clone().into());
+        }
+        if !self.totp_secret.is_empty() {
+            table.insert("totp_secret".to_string(), self.totp_secret.clone().into());
+        }
+        Ok(toml::to_string(&table)?)
+    }
+}
+
+/// Print `question` (with the default in brackets) and read one trimmed line.
+fn ask(input: &mut impl BufRead, output: &mut impl Write, question: &str, default: Option<&str>) -> Result<String, Box<dyn Error>> {
+    match default {
+        Some(default) => write!(output, "{} [{}]: ", question, default)?,
+        None => write!(output, "{}: ", question)?,
+    }
+    output.flush()?;
+    let mut line = String::new();
+    if input.read_line(&mut line)? == 0 {
+        return Err("Input ended before the setup was complete".into());
+    }
+    let answer = line.trim();
+    Ok(if answer.is_empty() { default.unwrap_or_default().to_string() } else { answer.to_string() })
+}
+
+fn ask_required(input: &mut impl BufRead, output: &mut impl Write, question: &str, default: Option<&str>) -> Result<String, Box<dyn Error>> {
+    loop {
+        let answer = ask(input, output, question, default)?;
+        if !answer.is_empty() {
+            return Ok(answer);
+        }
+        writeln!(output, "A value is required.")?;
+    }
+}
+
+fn ask_yes(input: &mut impl BufRead, output: &mut impl Write, question: &str) -> Result<bool, Box<dyn Error>> {
+    Ok(ask(input, output, &format!("{} (yes/No)", question), None)?.eq_ignore_ascii_case("yes"))
+}
+
+fn collect_answers(input: &mut impl BufRead, output: &mut impl Write) -> Result<InitAnswers, Box<dyn Error>> {
+    writeln!(output, "Create a \"script\" app at https://www.reddit.com/prefs/apps with the redirect uri")?;
+    writeln!(output, "{} and enter its details below.\n", auth::DEFAULT_REDIRECT_URI)?;
+
+    let username = ask_required(input, output, "Reddit username", None)?;
+    let default_user_agent = format!("bydit/{} by u/{}", env!("CARGO_PKG_VERSION"), username);
+    let user_agent = ask_required(input, output, "User agent", Some(&default_user_agent))?;
+    let client_id = ask_required(input, output, "Client ID (shown under the app name)", None)?;
+    let client_secret = ask(input, output, "Client secret", None)?;
+    let password = ask(
+        input,
+        output,
+        "Password (leave empty to authorize in the browser instead, recommended)",
+        None,
+    )?;
+    let two_factor = !password.is_empty() && ask_yes(input, output, "Does the account use two-factor authentication?")?;
+    let totp_secret = if two_factor {
+        ask(
+            input,
+            output,
+            "TOTP secret to generate codes from (leave empty to type a code now and use --otp-prompt later)",
+            None,
+        )?
+    } else {
+        String::new()
+    };
+
+    Ok(InitAnswers { user_agent, client_id, client_secret, username, password, two_factor, totp_secret })
+}
+
+/// A config file `load_config` finds before `written`, which would be used instead of it.
+fn shadowing_config(candidates: &[PathBuf], written: &Path) -> Option<PathBuf> {
+    candidates.iter().take_while(|candidate| candidate.as_path() != written).find(|candidate| candidate.exists()).cloned()
+}
+
+fn write_config(path: &Path, contents: &str) -> Result<(), Box<dyn Error>> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path).map_err(|e| {
+        let err_msg = format!("Failed to write config file '{}': {}", path.display(), e);
+        Box::new(io::Error::new(e.kind(), err_msg)) as Box<dyn Error>
+    })?;
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+/// Run the wizard on the terminal.
+pub async fn run_init(debug_mode: bool) -> Result<(), Box<dyn Error>> {
+    let path = config_dir()
+        .ok_or("Could not determine the config directory (HOME is unset)")?
+        .join(CONFIG_FILE);
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut output = io::stdout();
+
+    if path.exists() && !ask_yes(&mut input, &mut output, &format!("{} already exists. Overwrite it?", path.display()))? {
+        println!("Keeping the existing config file.");
+        return Ok(());
+    }
+
+    let answers = collect_answers(&mut input, &mut output)?;
+    let contents = answers.to_toml()?;
+    let config: Config = toml::from_str(&contents)?;
+
+    // Check the credentials before config.toml is written. Authorizing in the browser stores
+    // its refresh token right away, which is kept even if the config is not saved.
+    let prompt_otp = answers.two_factor && answers.totp_secret.is_empty();
+    let verified = async {
+        if answers.password.is_empty() {
+            auth::login(&config, debug_mode).await?;
+        }
+        connect_reddit(&config, prompt_otp, debug_mode).await
+    }
+    .await;
+    match verified {
+        Ok(api) => println!("\nCredentials verified for u/{}.", api.username()),
+        Err(e) => {
+            eprintln!("\nCould not log in with these credentials: {}", e);
+            if !ask_yes(&mut input, &mut output, "Save the config anyway?")? {
+                return Err("Setup aborted; no config file was written".into());
+            }
+        }
+    }
+
+    write_config(&path, &contents)?;
+    println!("Wrote {} (readable only by you).", path.display());
+    if prompt_otp {
+        println!("Pass --otp-prompt to be asked for a two-factor code when Bydit logs in.");
+    }
+    if let Some(shadowing) = shadowing_config(&candidate_paths(CONFIG_FILE), &path) {
+        eprintln!(
+            "Warning: {} is found first and will be used instead; remove or rename it to use the new config.",
+            shadowing.display()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn answers_become_a_loadable_config() -> Result<(), Box<dyn Error>> {
+        let mut input = io::Cursor::new("someone\n\nabc123\nsecret \"quoted\"\n\n");
+        let mut output = Vec::new();
+        let answers = collect_answers(&mut input, &mut output)?;
+        assert_eq!(answers.user_agent, format!("bydit/{} by u/someone", env!("CARGO_PKG_VERSION")));
+        assert!(answers.password.is_empty());
+
+        let config: Config = toml::from_str(&answers.to_toml()?)?;
+        assert_eq!(config.client_secret, "secret \"quoted\"");
+        assert_eq!(config.username, "someone");
+        assert!(config.password.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn two_factor_secret_is_written() -> Result<(), Box<dyn Error>> {
+        let mut input = io::Cursor::new("someone\n\nabc123\n\nhunter2\nyes\nJBSWY3DPEHPK3PXP\n");
+        let answers = collect_answers(&mut input, &mut Vec::new())?;
+        assert!(answers.two_factor);
+        let config: Config = toml::from_str(&answers.to_toml()?)?;
+        assert_eq!(config.totp_secret.as_deref(), Some("JBSWY3DPEHPK3PXP"));
+        Ok(())
+    }
+
+    #[test]
+    fn config_in_the_current_directory_shadows_the_written_one() -> Result<(), Box<dyn Error>> {
+        let temp = tempdir()?;
+        let local = temp.path().join(CONFIG_FILE);
+        let written = temp.path().join("bydit").join(CONFIG_FILE);
+        let candidates = vec![local.clone(), written.clone()];
+        assert_eq!(shadowing_config(&candidates, &written), None);
+        fs::write(&local, "")?;
+        assert_eq!(shadowing_config(&candidates, &written), Some(local));
+        Ok(())
+    }
+
+    #[test]
+    fn required_answers_are_asked_again() -> Result<(), Box<dyn Error>> {
+        let mut input = io::Cursor::new("\nsomeone\n");
+        let mut output = Vec::new();
+        assert_eq!(ask_required(&mut input, &mut output, "Reddit username", None)?, "someone");
+        assert!(String::from_utf8(output)?.contains("A value is required."));
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn config_is_written_owner_only() -> Result<(), Box<dyn Error>> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempdir()?;
+        let path = temp.path().join("bydit").join(CONFIG_FILE);
+        write_config(&path, "user_agent = \"ua\"\n")?;
+        assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+        Ok(())
+    }
+}
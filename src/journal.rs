@@ -0,0 +1,231 @@
+// Append-only action journal used to resume interrupted overwrite/delete runs.
+//
+// Every line is a JSON object recording one state change of one item. The last line for an
+// (action, id) pair wins, so replaying the file yields the current state of every item.
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+const JOURNAL_DIR: &str = "journals";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum JournalAction {
+    Overwrite,
+    Delete,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JournalResult {
+    Pending,
+    Done,
+    Failed,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalEntry {
+    pub id: String,
+    pub action: JournalAction,
+    pub timestamp: String,
+    pub result: JournalResult,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+    states: HashMap<(JournalAction, String), JournalResult>,
+    /// Item ids in the order they were first recorded, so resumed runs keep the original order.
+    order: Vec<String>,
+    known_ids: HashSet<String>,
+}
+
+impl Journal {
+    /// Start a new journal file under `<data_dir>/journals/`.
+    pub fn create(data_dir: &Path) -> Result<Journal, Box<dyn Error>> {
+        let journal_dir = data_dir.join(JOURNAL_DIR);
+        fs::create_dir_all(&journal_dir).map_err(|e| {
+            let err_msg = format!("Failed to create journal directory '{}': {}", journal_dir.display(), e);
+            Box::new(io::Error::new(e.kind(), err_msg)) as Box<dyn Error>
+        })?;
+        let file_name = format!("{}.jsonl", Utc::now().format("%Y%m%dT%H%M%S%.3fZ"));
+        Self::open(&journal_dir.join(file_name))
+    }
+
+    /// Open an existing journal (or create it if missing) and replay its entries.
+    pub fn open(path: &Path) -> Result<Journal, Box<dyn Error>> {
+        let mut states = HashMap::new();
+        let mut order = Vec::new();
+        let mut known_ids = HashSet::new();
+
+        if path.exists() {
+            let reader = BufReader::new(File::open(path)?);
+            for (line_number, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let entry: JournalEntry = serde_json::from_str(&line).map_err(|e| {
+                    let err_msg = format!(
+                        "Failed to parse journal '{}' at line {}: {}",
+                        path.display(),
+                        line_number + 1,
+                        e
+                    );
+                    Box::new(io::Error::new(io::ErrorKind::InvalidData, err_msg)) as Box<dyn Error>
+                })?;
+                if known_ids.insert(entry.id.clone()) {
+                    order.push(entry.id.clone());
+                }
+                states.insert((entry.action, entry.id), entry.result);
+            }
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path).map_err(|e| {
+            let err_msg = format!("Failed to open journal '{}': {}", path.display(), e);
+            Box::new(io::Error::new(e.kind(), err_msg)) as Box<dyn Error>
+        })?;
+
+        Ok(Journal {
+            path: path.to_path_buf(),
+            file,
+            states,
+            order,
+            known_ids,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append a state change for one item and flush it to disk immediately.
+    pub fn record(
+        &mut self,
+        id: &str,
+        action: JournalAction,
+        result: JournalResult,
+        error: Option<String>,
+    ) -> Result<(), Box<dyn Error>> {
+        let entry = JournalEntry {
+            id: id.to_string(),
+            action,
+            timestamp: Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            result,
+            error,
+        };
+        let line = serde_json::to_string(&entry)?;
+        writeln!(self.file, "{}", line)?;
+        self.file.flush()?;
+
+        if self.known_ids.insert(id.to_string()) {
+            self.order.push(id.to_string());
+        }
+        self.states.insert((action, id.to_string()), result);
+        Ok(())
+    }
+
+    pub fn is_done(&self, action: JournalAction, id: &str) -> bool {
+        self.states.get(&(action, id.to_string())) == Some(&JournalResult::Done)
+    }
+
+    /// Ids recorded for `action` whose last state is pending or failed.
+    pub fn unfinished(&self, action: JournalAction) -> Vec<String> {
+        self.order
+            .iter()
+            .filter(|id| {
+                matches!(
+                    self.states.get(&(action, id.to_string())),
+                    Some(JournalResult::Pending) | Some(JournalResult::Failed)
+                )
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Ids whose `first` action is done but that have no entry for `then` yet: a run doing
+    /// both was interrupted before it reached `then` for them.
+    pub fn awaiting(&self, first: JournalAction, then: JournalAction) -> Vec<String> {
+        self.order
+            .iter()
+            .filter(|id| {
+                self.states.get(&(first, id.to_string())) == Some(&JournalResult::Done)
+                    && !self.states.contains_key(&(then, id.to_string()))
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Number of items recorded as done for `action`.
+    pub fn done_count(&self, action: JournalAction) -> usize {
+        self.states
+            .iter()
+            .filter(|((entry_action, _), result)| *entry_action == action && **result == JournalResult::Done)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn replays_last_state_per_item() -> Result<(), Box<dyn Error>> {
+        let temp = tempdir()?;
+        let path = {
+            let mut journal = Journal::create(temp.path())?;
+            for id in ["t1_a", "t1_b", "t1_c"] {
+                journal.record(id, JournalAction::Delete, JournalResult::Pending, None)?;
+            }
+            journal.record("t1_a", JournalAction::Delete, JournalResult::Done, None)?;
+            journal.record("t1_b", JournalAction::Delete, JournalResult::Failed, Some("500".to_string()))?;
+            journal.path().to_path_buf()
+        };
+        assert!(path.starts_with(temp.path().join(JOURNAL_DIR)));
+
+        let resumed = Journal::open(&path)?;
+        assert!(resumed.is_done(JournalAction::Delete, "t1_a"));
+        assert!(!resumed.is_done(JournalAction::Overwrite, "t1_a"));
+        assert_eq!(resumed.unfinished(JournalAction::Delete), vec!["t1_b", "t1_c"]);
+        assert_eq!(resumed.done_count(JournalAction::Delete), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn items_overwritten_before_an_interruption_await_deletion() -> Result<(), Box<dyn Error>> {
+        let temp = tempdir()?;
+        let path = {
+            let mut journal = Journal::create(temp.path())?;
+            for id in ["t1_a", "t1_b", "t1_c"] {
+                journal.record(id, JournalAction::Overwrite, JournalResult::Pending, None)?;
+            }
+            journal.record("t1_a", JournalAction::Overwrite, JournalResult::Done, None)?;
+            journal.record("t1_b", JournalAction::Overwrite, JournalResult::Done, None)?;
+            journal.record("t1_b", JournalAction::Delete, JournalResult::Done, None)?;
+            // Interrupted here: t1_a is overwritten but was never deleted
+            journal.path().to_path_buf()
+        };
+
+        let resumed = Journal::open(&path)?;
+        assert_eq!(resumed.unfinished(JournalAction::Overwrite), vec!["t1_c"]);
+        assert!(resumed.unfinished(JournalAction::Delete).is_empty());
+        assert_eq!(resumed.awaiting(JournalAction::Overwrite, JournalAction::Delete), vec!["t1_a"]);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_corrupt_lines() -> Result<(), Box<dyn Error>> {
+        let temp = tempdir()?;
+        let path = temp.path().join("broken.jsonl");
+        fs::write(&path, "not json\n")?;
+        assert!(Journal::open(&path).is_err());
+        Ok(())
+    }
+}
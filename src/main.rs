@@ -1,61 +1,184 @@
 use clap::Parser; // Cli::parse() is used in main
+use roux::me::response::MeData;
+use std::collections::HashSet;
 use std::error::Error;
+use std::path::Path;
 // std::io::{self, Write}; // Moved to actions.rs
-use tokio;
+
+mod api;
+mod auth;
+use api::RedditApi;
 
 mod config;
-use config::load_config;
+use config::{config_dir, data_dir, fix_config_permissions, load_config, Config};
+
+mod secrets;
+use secrets::resolve_secrets;
 
 mod reddit_ops;
-use reddit_ops::{connect_reddit, fetch_user_items};
+use reddit_ops::{connect_reddit, fetch_user_items, hydrate_items};
 
 mod actions;
-use actions::{handle_overwrite_action, handle_delete_action, handle_csv_export, handle_print_to_console};
+use actions::{handle_overwrite_action, handle_delete_action, handle_bulk_action, BulkAction, handle_csv_export, handle_json_export, handle_print_to_console, handle_backups_output, ActionContext};
+
+mod archive;
+use archive::SqliteArchive;
+
+mod backup;
+use backup::{BackupArchive, BACKUP_FILE};
 
 mod cli;
-use cli::Cli;
+use cli::{BackupsCommand, Cli, Command, ConfigCommand, OutputFormat, Source};
+
+mod filter_expr;
+mod filters;
+mod gdpr;
+mod init;
+use gdpr::load_gdpr_export;
+
+use filter_expr::Expr;
+use filters::{flag_condition, ContentFilters, ItemFilters};
+
+mod journal;
+use journal::{Journal, JournalAction};
 
 mod models;
+use models::UnifiedItem;
+
+mod policy;
+use policy::{apply_policy, Policy};
+
+mod protect;
+use protect::{ProtectList, PROTECT_FILE};
+
+mod totp;
 mod utils;
-use utils::parse_age_to_timestamp;
+use utils::{parse_age_to_timestamp, profile_output_path};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     let cli = Cli::parse();
 
-    let config = load_config("config.toml", cli.debug)?;
+    // Subcommands work on local state or run their own login flow
+    if let Some(command) = &cli.command {
+        return run_command(command, &cli).await;
+    }
+
+    // Check the filter expression and content filters before logging in
+    let mut conditions = match cli.where_expr.as_deref().map(filter_expr::parse).transpose() {
+        Ok(expr) => Vec::from_iter(expr),
+        Err(e) => {
+            eprintln!("Error parsing --where: {}", e);
+            return Err(e.into());
+        }
+    };
+    let content_filters = ContentFilters {
+        content_regex: cli.content_regex.clone(),
+        exclude_content_regex: cli.exclude_content_regex.clone(),
+        title_regex: cli.title_regex.clone(),
+        exclude_title_regex: cli.exclude_title_regex.clone(),
+        keyword_file: cli.keyword_file.clone(),
+        exclude_keyword_file: cli.exclude_keyword_file.clone(),
+        case_sensitive: cli.case_sensitive,
+        whole_word: cli.whole_word,
+    };
+    conditions.extend(content_filters.conditions()?);
+    conditions.extend(cli.with_flag.iter().map(|flag| flag_condition(*flag)));
+    conditions.extend(cli.without_flag.iter().map(|flag| Expr::Not(Box::new(flag_condition(*flag)))));
+
+    let profiles = load_config("config.toml", cli.strict_permissions, cli.debug)?;
 
-    let reddit = connect_reddit(&config, cli.debug).await?;
+    if cli.all_profiles {
+        run_all_profiles(&cli, profiles.all(), &conditions).await?;
+    } else {
+        let config = profiles.select(cli.profile.as_deref())?;
+        run_account(&cli, &config, &conditions).await?;
+    }
+
+    if cli.debug { println!("\nApplication finished."); }
+    Ok(())
+}
+
+/// What one account's run did, for the `--all-profiles` summary.
+struct AccountReport {
+    username: String,
+    items: usize,
+    deleted: Option<usize>,
+}
+
+/// Run the same fetch, filters and actions for every profile. A failing account is reported
+/// and does not stop the others.
+async fn run_all_profiles(cli: &Cli, configs: &[Config], conditions: &[Expr]) -> Result<(), Box<dyn Error>> {
+    let mut reports = Vec::new();
+    for config in configs {
+        eprintln!("\n=== Profile {} ===", config.profile_name());
+        let report = run_account(cli, config, conditions).await;
+        if let Err(e) = &report {
+            eprintln!("Profile {} failed: {}", config.profile_name(), e);
+        }
+        reports.push((config.profile_name(), report));
+    }
+
+    eprintln!("\n--- Per-Profile Summary ---");
+    let mut failed_count = 0;
+    for (profile, report) in &reports {
+        match report {
+            Ok(report) => {
+                let deleted = report.deleted.map(|count| format!(", {} deleted", count)).unwrap_or_default();
+                eprintln!("  {:<16} u/{}: {} items{}", profile, report.username, report.items, deleted);
+            }
+            Err(e) => {
+                eprintln!("  {:<16} failed: {}", profile, e);
+                failed_count += 1;
+            }
+        }
+    }
+    if failed_count > 0 {
+        return Err(format!("{} of {} profiles failed", failed_count, reports.len()).into());
+    }
+    Ok(())
+}
+
+/// Log in to one account and run the fetch, filters, actions and output for it.
+async fn run_account(cli: &Cli, config: &Config, conditions: &[Expr]) -> Result<AccountReport, Box<dyn Error>> {
+    let protect = load_protect_list(cli)?;
+    let mut config = config.clone();
+    resolve_secrets(&mut config, cli.debug)?;
+
+    let api = connect_reddit(&config, cli.otp_prompt, cli.debug).await?;
 
     // Get the authenticated user's information
-    let authenticated_username: &str = reddit.config.username.as_deref().ok_or_else(|| {
-        let err_msg = "Username is None in config after successful login and Me retrieval.";
-        if cli.debug { eprintln!("{}", err_msg); }
-        Box::<dyn Error>::from(err_msg)
-    })?;
+    let authenticated_username: &str = api.username();
     if cli.debug { println!("Successfully logged in and using username: {}", authenticated_username); }
 
-    // Fetch additional account metadata using the me() endpoint
-    let me_data = reddit.me().await.map_err(|e| {
+    // Fetch additional account metadata using the api/v1/me endpoint
+    let me_data: MeData = api.get_json("api/v1/me").await.map_err(|e| {
         if cli.debug {
-            eprintln!("\nFailed to fetch your user data (me()): {}", e);
+            eprintln!("\nFailed to fetch your user data (api/v1/me): {}", e);
         }
-        Box::new(e) as Box<dyn Error>
+        e
     })?;
     if cli.debug { println!("Successfully retrieved account metadata (Reddit ID: {}) for user: {}", me_data.id, authenticated_username); }
 
-    // The `reddit` object (type `roux::Reddit` after successful login) can be used for actions
-    // like edit, comment, etc., as it holds the authenticated state.
-    // The `me_data` variable (type `roux::models::me::MeData`) contains user-specific information.
+    // Every request below goes through `api`, which holds the access token and paces
+    // requests according to Reddit's rate-limit headers.
 
     // Determine what to fetch
-    let fetch_posts = cli.item_type.as_ref().map_or(true, |t| t.eq_ignore_ascii_case("post") || t.eq_ignore_ascii_case("posts") || t.eq_ignore_ascii_case("both"));
-    let fetch_comments = cli.item_type.as_ref().map_or(true, |t| t.eq_ignore_ascii_case("comment") || t.eq_ignore_ascii_case("comments") || t.eq_ignore_ascii_case("both"));
+    let fetch_posts = cli.item_type.as_ref().is_none_or(|t| t.eq_ignore_ascii_case("post") || t.eq_ignore_ascii_case("posts") || t.eq_ignore_ascii_case("both"));
+    let fetch_comments = cli.item_type.as_ref().is_none_or(|t| t.eq_ignore_ascii_case("comment") || t.eq_ignore_ascii_case("comments") || t.eq_ignore_ascii_case("both"));
+
+    // Without --source, fetch the user's own posts and/or comments
+    let sources: Vec<Source> = if cli.source.is_empty() {
+        let mut own_sources = Vec::new();
+        if fetch_posts { own_sources.push(Source::Submitted); }
+        if fetch_comments { own_sources.push(Source::Comments); }
+        own_sources
+    } else {
+        cli.source.clone()
+    };
 
     if cli.debug {
-        let mut fetching_what = Vec::new();
-        if fetch_posts { fetching_what.push("posts"); }
-        if fetch_comments { fetching_what.push("comments"); }
+        let fetching_what: Vec<&str> = sources.iter().map(|source| source.as_str()).collect();
 
         if fetching_what.is_empty() {
             // This case should ideally not be reached if item_type defaults or is "both"
@@ -100,12 +223,7 @@ async fn main() -> Result<(), Box<dyn Error>> {
         None
     };
 
-    // Fetch items
-    let mut all_items = fetch_user_items(
-        &reddit,
-        authenticated_username,
-        fetch_posts,
-        fetch_comments,
+    let mut filters = ItemFilters::new(
         cli.subreddit.as_ref(),
         cli.exclude_subreddit.as_ref(),
         cli.score,
@@ -113,22 +231,91 @@ async fn main() -> Result<(), Box<dyn Error>> {
         min_age_timestamp,
         max_age_timestamp,
         cli.post_title.as_ref(),
-        cli.debug,
-    )
-    .await?;
+    );
+    for condition in conditions {
+        filters = filters.with_expression(condition.clone());
+    }
+    if cli.debug { println!("Filters: {}", filters); }
+
+    // Open the action journal before anything is changed, so an interrupted run can be resumed
+    let journal = open_journal(cli)?;
+    let backup = open_backup_archive(cli, cli.overwrite.is_some() || cli.delete)?;
+    let mut action_ctx = ActionContext {
+        skip_confirmation: cli.yes,
+        dry_run: cli.dry_run,
+        journal,
+        backup,
+        archive: None,
+        protect,
+        debug_mode: cli.debug,
+    };
+
+    // Fetch items, either from a journal being resumed, a data export or the listings
+    let mut all_items = if let (Some(journal), Some(_)) = (action_ctx.journal.as_ref(), &cli.resume) {
+        load_resume_items(&api, journal, cli).await?
+    } else if let Some(export_path) = &cli.gdpr_export {
+        let mut imported = load_gdpr_export(Path::new(export_path), fetch_posts, fetch_comments, cli.debug)?;
+        eprintln!("Imported {} items from data export {}.", imported.len(), export_path);
+        hydrate_items(&api, &mut imported, cli.debug).await?;
+        imported.retain(|item| filters.matches(item));
+        imported
+    } else {
+        fetch_user_items(
+            &api,
+            authenticated_username,
+            &sources,
+            fetch_posts,
+            fetch_comments,
+            &filters,
+            cli.all_sorts,
+            cli.max_pages,
+            cli.debug,
+        )
+        .await?
+    };
 
     // Sort all items by creation date (newest first)
     all_items.sort_by(|a, b| b.created_utc.partial_cmp(&a.created_utc).unwrap_or(std::cmp::Ordering::Equal));
 
+    // Record everything fetched in the SQLite archive before any item is changed
+    if let Some(sqlite_path) = &cli.sqlite {
+        let mut archive = SqliteArchive::open(Path::new(sqlite_path))?;
+        archive.upsert_items(&all_items)?;
+        if cli.debug {
+            println!("Archived {} items in {}", all_items.len(), archive.path().display());
+        }
+        action_ctx.archive = Some(archive);
+    }
+
+    // With --all-profiles every account writes its own file
+    let output_path = cli.csv.as_ref().or(cli.output.as_ref()).map(|path| {
+        if cli.all_profiles { profile_output_path(path, config.profile_name()) } else { path.clone() }
+    });
+
     // If overwrite is requested, perform it first. If delete is also requested, proceed to deletion next.
+    // Archived items cannot be edited; the overwrite skips them and --delete removes them
     if let Some(overwrite_text) = &cli.overwrite {
-        handle_overwrite_action(&reddit, &mut all_items, overwrite_text, cli.debug).await?;
+        handle_overwrite_action(&api, &mut all_items, overwrite_text, &mut action_ctx).await?;
+    }
+
+    // Unsave/unvote/unhide run before deletion, while the items still exist
+    let mut bulk_actions = Vec::new();
+    if cli.unsave { bulk_actions.push(BulkAction::Unsave); }
+    if cli.unvote { bulk_actions.push(BulkAction::Unvote); }
+    if cli.unhide { bulk_actions.push(BulkAction::Unhide); }
+    for action in &bulk_actions {
+        handle_bulk_action(&api, &all_items, *action, &action_ctx).await?;
     }
 
+    let mut deleted = None;
     if cli.delete {
-        let _deleted_items_count = handle_delete_action(&reddit, &all_items, cli.yes, cli.debug).await?;
-        // The function now prints its own summary.
-    } else if let Some(csv_file_path) = &cli.csv {
+        // The function prints its own summary.
+        deleted = Some(handle_delete_action(&api, &all_items, &mut action_ctx).await?.len());
+    } else if !bulk_actions.is_empty() {
+        if cli.debug { println!("\nFinished bulk actions; skipping item output."); }
+    } else if cli.format != OutputFormat::Csv {
+        handle_json_export(&all_items, cli.format, output_path.as_deref(), cli.debug)?;
+    } else if let Some(csv_file_path) = &output_path {
         if all_items.is_empty() {
             if cli.debug {
                 println!("No items to export to CSV based on current filters.");
@@ -141,6 +328,176 @@ async fn main() -> Result<(), Box<dyn Error>> {
         if cli.debug { println!("\nFinished processing and printing data."); }
     }
 
-    if cli.debug { println!("\nApplication finished."); }
-    Ok(())
+    Ok(AccountReport {
+        username: authenticated_username.to_string(),
+        items: all_items.len(),
+        deleted,
+    })
+}
+
+async fn run_command(command: &Command, cli: &Cli) -> Result<(), Box<dyn Error>> {
+    let debug_mode = cli.debug;
+    match command {
+        Command::Init => init::run_init(debug_mode).await,
+        Command::Login => {
+            let mut config = load_config("config.toml", cli.strict_permissions, debug_mode)?.select(cli.profile.as_deref())?;
+            resolve_secrets(&mut config, debug_mode)?;
+            if debug_mode { println!("Logging in for profile {}", config.profile_name()); }
+            auth::login(&config, debug_mode).await
+        }
+        Command::ApplyPolicy { rules } => {
+            // Validate everything before logging in
+            let policy = Policy::load(Path::new(rules))?;
+            let protect = load_protect_list(cli)?;
+            let mut config = load_config("config.toml", cli.strict_permissions, debug_mode)?.select(cli.profile.as_deref())?;
+            resolve_secrets(&mut config, debug_mode)?;
+            let api = connect_reddit(&config, cli.otp_prompt, debug_mode).await?;
+            if debug_mode { println!("Applying policy {} as u/{}", rules, api.username()); }
+
+            // Policy runs cannot be resumed; the journal only records what was changed
+            let journal = if policy.modifies_items() && !cli.dry_run { create_journal(false)? } else { None };
+            let mut action_ctx = ActionContext {
+                skip_confirmation: cli.yes,
+                dry_run: cli.dry_run,
+                journal,
+                backup: open_backup_archive(cli, policy.modifies_items())?,
+                archive: None,
+                protect,
+                debug_mode,
+            };
+            apply_policy(&api, &policy, &mut action_ctx, debug_mode).await
+        }
+        Command::Config { action: ConfigCommand::FixPermissions } => {
+            let path = fix_config_permissions("config.toml")?;
+            println!("Restricted {} to mode 0600 (read and write for the owner only).", path.display());
+            Ok(())
+        }
+        Command::Backups { action } => {
+            let backup_path = data_dir()
+                .ok_or("Could not determine the data directory (HOME is unset)")?
+                .join(BACKUP_FILE);
+            match action {
+                BackupsCommand::List { limit } => handle_backups_output(&backup_path, None, *limit, debug_mode),
+                BackupsCommand::Search { text, limit } => {
+                    handle_backups_output(&backup_path, Some(text), *limit, debug_mode)
+                }
+            }
+        }
+    }
+}
+
+/// Open the archive that keeps a copy of every item before it is overwritten or deleted.
+fn open_backup_archive(cli: &Cli, modifies_items: bool) -> Result<Option<BackupArchive>, Box<dyn Error>> {
+    if !modifies_items || cli.dry_run {
+        return Ok(None);
+    }
+    let dir = data_dir().ok_or("Could not determine a data directory for backups (HOME is unset); refusing to modify items without a backup")?;
+    let archive = BackupArchive::open(&dir)?;
+    if cli.debug {
+        println!("Backing up items to {} before changing them.", archive.path().display());
+    }
+    Ok(Some(archive))
+}
+
+/// The protect list from `--protect-file` or the config directory, with the built-in rules
+/// of `--protect-score-above` and `--protect-gilded`.
+fn load_protect_list(cli: &Cli) -> Result<ProtectList, Box<dyn Error>> {
+    let mut protect = match &cli.protect_file {
+        Some(path) => ProtectList::load(Path::new(path))?,
+        None => match config_dir().map(|dir| dir.join(PROTECT_FILE)) {
+            Some(path) if path.exists() => ProtectList::load(&path)?,
+            _ => ProtectList::default(),
+        },
+    };
+    protect.score_above = cli.protect_score_above;
+    protect.gilded = cli.protect_gilded;
+    if cli.debug && !protect.is_empty() { println!("Protection rules are active; protected items will be skipped by every action."); }
+    Ok(protect)
+}
+
+/// Open the journal given to `--resume`, or start a new one for runs that overwrite or delete.
+fn open_journal(cli: &Cli) -> Result<Option<Journal>, Box<dyn Error>> {
+    let modifies_items = cli.overwrite.is_some() || cli.delete;
+    if let Some(resume_path) = &cli.resume {
+        if !modifies_items {
+            return Err("--resume requires --overwrite and/or --delete".into());
+        }
+        if cli.dry_run {
+            return Err("--resume cannot be combined with --dry-run".into());
+        }
+        let journal = Journal::open(Path::new(resume_path))?;
+        eprintln!("Resuming from journal {}", journal.path().display());
+        return Ok(Some(journal));
+    }
+    if !modifies_items || cli.dry_run {
+        return Ok(None);
+    }
+    create_journal(true)
+}
+
+/// Start a new journal in the data directory. With `resumable`, print how to resume from it.
+fn create_journal(resumable: bool) -> Result<Option<Journal>, Box<dyn Error>> {
+    match data_dir() {
+        Some(dir) => {
+            let journal = Journal::create(&dir)?;
+            if resumable {
+                eprintln!(
+                    "Recording progress in {} (continue an interrupted run with --resume {})",
+                    journal.path().display(),
+                    journal.path().display()
+                );
+            } else {
+                eprintln!("Recording progress in {}", journal.path().display());
+            }
+            Ok(Some(journal))
+        }
+        None => {
+            eprintln!("Warning: could not determine a data directory (HOME is unset); running without a journal.");
+            Ok(None)
+        }
+    }
+}
+
+/// Rebuild the work list of an interrupted run from the pending and failed entries of its journal.
+async fn load_resume_items(api: &RedditApi, journal: &Journal, cli: &Cli) -> Result<Vec<UnifiedItem>, Box<dyn Error>> {
+    let mut actions = Vec::new();
+    if cli.overwrite.is_some() {
+        actions.push(JournalAction::Overwrite);
+    }
+    if cli.delete {
+        actions.push(JournalAction::Delete);
+    }
+
+    let mut ids: Vec<String> = Vec::new();
+    let mut seen_ids: HashSet<String> = HashSet::new();
+    for action in &actions {
+        eprintln!(
+            "Journal {:?}: {} items already completed, {} pending or failed.",
+            action,
+            journal.done_count(*action),
+            journal.unfinished(*action).len()
+        );
+        for id in journal.unfinished(*action) {
+            if seen_ids.insert(id.clone()) {
+                ids.push(id);
+            }
+        }
+    }
+    // A run interrupted while overwriting never journaled the deletes of the items it had
+    // already overwritten
+    if cli.overwrite.is_some() && cli.delete {
+        let awaiting = journal.awaiting(JournalAction::Overwrite, JournalAction::Delete);
+        eprintln!("Journal Delete: {} overwritten items not deleted yet.", awaiting.len());
+        for id in awaiting {
+            if seen_ids.insert(id.clone()) {
+                ids.push(id);
+            }
+        }
+    }
+
+    let mut items: Vec<UnifiedItem> = ids.iter().map(|id| UnifiedItem::from_fullname(id)).collect();
+    if !items.is_empty() {
+        hydrate_items(api, &mut items, cli.debug).await?;
+    }
+    Ok(items)
 }
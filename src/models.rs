@@ -1,12 +1,67 @@
-#[derive(Debug, Clone)] // Added Debug and Clone for general utility
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UnifiedItem {
     pub id: String, // Full Reddit ID, e.g., t3_xxxxxx or t1_xxxxxx
-    pub item_type: String, // "Post" or "Comment"
+    pub item_type: String, // "Post", "Comment" or "Message"
     pub subreddit: String,
-    pub title: String,       // Post title or link title for comment
-    pub content: String,     // Post selftext or comment body
+    pub title: String,       // Post title, link title for comment or message subject
+    pub content: String,     // Post selftext, comment or message body
     pub upvotes: i32,
     pub num_comments: i32, // For posts; 0 for comments
     pub permalink: String,
     pub created_utc: f64,  // Timestamp for sorting
+    #[serde(default)]
+    pub source: String,    // Listing the item came from, e.g. "submitted", "comments" or "saved"
+    #[serde(default)]
+    pub author: String,    // Messages only: sender
+    #[serde(default)]
+    pub recipient: String, // Messages only: recipient (a user, or "#subreddit")
+    #[serde(default)]
+    pub gilded: i32,       // Times the item was awarded gold
+    #[serde(default, flatten)]
+    pub flags: ItemFlags,
+}
+
+/// Status flags Reddit reports for posts and comments. Messages have none set.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ItemFlags {
+    pub edited: bool,
+    pub stickied: bool,
+    pub locked: bool,
+    pub over_18: bool,
+    pub spoiler: bool,       // Posts only
+    pub archived: bool,      // Archived items can no longer be edited, only deleted
+    pub distinguished: String, // "moderator", "admin" or empty
+}
+
+impl UnifiedItem {
+    /// Placeholder for an item only known by its fullname (e.g. from a journal), to be filled
+    /// in via `hydrate_items`.
+    pub fn from_fullname(id: &str) -> Self {
+        let (item_type, source) = if id.starts_with("t3_") {
+            ("Post", "submitted")
+        } else if id.starts_with("t4_") {
+            ("Message", "messages")
+        } else {
+            ("Comment", "comments")
+        };
+        UnifiedItem {
+            id: id.to_string(),
+            item_type: item_type.to_string(),
+            subreddit: String::new(),
+            title: String::new(),
+            content: String::new(),
+            upvotes: 0,
+            num_comments: 0,
+            permalink: String::new(),
+            created_utc: 0.0,
+            source: source.to_string(),
+            author: String::new(),
+            recipient: String::new(),
+            gilded: 0,
+            flags: ItemFlags::default(),
+        }
+    }
 }
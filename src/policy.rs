@@ -0,0 +1,304 @@
+// Retention policies: `bydit apply-policy <rules.toml>`.
+//
+// A rules file lists `[[rule]]` tables, each selecting items from its own listings with the
+// usual filters and applying one action to them:
+//
+//   [[rule]]
+//   name = "old comments in busy subreddits"
+//   source = ["comments"]
+//   subreddit = ["rust", "golang"]
+//   min_age = "90 days"
+//   action = "delete"
+//
+// Every listing is fetched once per run, however many rules use it. Rules run in file order
+// and an item deleted by one rule is not seen by later rules.
+use crate::actions::{handle_csv_export, handle_delete_action, handle_json_export, handle_overwrite_action, ActionContext};
+use crate::api::RedditApi;
+use crate::cli::{OutputFormat, Source};
+use crate::filter_expr;
+use crate::filters::ItemFilters;
+use crate::models::UnifiedItem;
+use crate::reddit_ops::fetch_user_items;
+use crate::utils::parse_age_to_timestamp;
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PolicyFile {
+    #[serde(default)]
+    rule: Vec<RuleConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ActionKind {
+    Overwrite,
+    Delete,
+    Export,
+}
+
+/// One `[[rule]]` as written in the file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RuleConfig {
+    name: String,
+    #[serde(default = "default_sources")]
+    source: Vec<String>,
+    #[serde(default)]
+    subreddit: Vec<String>,
+    #[serde(default)]
+    exclude_subreddit: Vec<String>,
+    min_score: Option<i32>,
+    max_score: Option<i32>,
+    min_age: Option<String>,
+    max_age: Option<String>,
+    #[serde(rename = "where")]
+    where_expr: Option<String>,
+    action: ActionKind,
+    /// Replacement text for `action = "overwrite"`.
+    text: Option<String>,
+    /// File written by `action = "export"`.
+    output: Option<String>,
+    /// `csv` (default), `json` or `ndjson` for `action = "export"`.
+    format: Option<String>,
+}
+
+fn default_sources() -> Vec<String> {
+    vec![Source::Submitted.as_str().to_string(), Source::Comments.as_str().to_string()]
+}
+
+#[derive(Debug)]
+enum RuleAction {
+    Overwrite(String),
+    Delete,
+    Export { output: String, format: OutputFormat },
+}
+
+/// A validated rule, ready to run.
+#[derive(Debug)]
+pub struct PolicyRule {
+    name: String,
+    sources: Vec<Source>,
+    filters: ItemFilters,
+    action: RuleAction,
+}
+
+/// What one rule did, for the report printed at the end of the run.
+struct RuleReport {
+    name: String,
+    action: &'static str,
+    matched: usize,
+    outcome: String,
+}
+
+#[derive(Debug)]
+pub struct Policy {
+    rules: Vec<PolicyRule>,
+}
+
+impl Policy {
+    /// Read and validate a rules file, so mistakes are reported before logging in.
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read rules file '{}': {}", path.display(), e))?;
+        Self::parse(&contents).map_err(|e| format!("Invalid rules file '{}': {}", path.display(), e).into())
+    }
+
+    fn parse(contents: &str) -> Result<Self, Box<dyn Error>> {
+        let file: PolicyFile = toml::from_str(contents)?;
+        if file.rule.is_empty() {
+            return Err("no [[rule]] entries".into());
+        }
+        let rules = file
+            .rule
+            .into_iter()
+            .map(|rule| {
+                let name = rule.name.clone();
+                compile_rule(rule).map_err(|e| format!("rule \"{}\": {}", name, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Policy { rules })
+    }
+
+    /// Whether any rule changes items on Reddit (and so needs a journal and backups).
+    pub fn modifies_items(&self) -> bool {
+        self.rules.iter().any(|rule| !matches!(rule.action, RuleAction::Export { .. }))
+    }
+}
+
+fn compile_rule(rule: RuleConfig) -> Result<PolicyRule, Box<dyn Error>> {
+    let sources = rule
+        .source
+        .iter()
+        .map(|name| {
+            Source::from_str(name, true).map_err(|_| {
+                let valid: Vec<&str> = Source::value_variants().iter().map(|source| source.as_str()).collect();
+                format!("unknown source `{}` (expected one of {})", name, valid.join(", "))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if sources.is_empty() {
+        return Err("`source` must name at least one listing".into());
+    }
+
+    let min_age_timestamp = rule.min_age.as_deref().map(parse_age_to_timestamp).transpose()?;
+    let max_age_timestamp = rule.max_age.as_deref().map(parse_age_to_timestamp).transpose()?;
+    let subreddit = (!rule.subreddit.is_empty()).then(|| rule.subreddit.join(","));
+    let exclude_subreddit = (!rule.exclude_subreddit.is_empty()).then(|| rule.exclude_subreddit.join(","));
+    let mut filters = ItemFilters::new(
+        subreddit.as_ref(),
+        exclude_subreddit.as_ref(),
+        rule.min_score,
+        rule.max_score,
+        min_age_timestamp,
+        max_age_timestamp,
+        None,
+    );
+    if let Some(expression) = &rule.where_expr {
+        filters = filters.with_expression(filter_expr::parse(expression).map_err(|e| format!("invalid `where`: {}", e))?);
+    }
+
+    let action = match rule.action {
+        ActionKind::Overwrite => RuleAction::Overwrite(rule.text.ok_or("`action = \"overwrite\"` needs `text`")?),
+        ActionKind::Delete => RuleAction::Delete,
+        ActionKind::Export => {
+            let format = match rule.format.as_deref() {
+                Some(name) => OutputFormat::from_str(name, true)
+                    .map_err(|_| format!("unknown format `{}` (expected csv, json or ndjson)", name))?,
+                None => OutputFormat::Csv,
+            };
+            RuleAction::Export { output: rule.output.ok_or("`action = \"export\"` needs `output`")?, format }
+        }
+    };
+
+    Ok(PolicyRule { name: rule.name, sources, filters, action })
+}
+
+/// Run every rule against the authenticated account and print a per-rule report.
+pub async fn apply_policy(
+    api: &RedditApi,
+    policy: &Policy,
+    ctx: &mut ActionContext,
+    debug_mode: bool,
+) -> Result<(), Box<dyn Error>> {
+    // Each listing is fetched unfiltered once and shared by all rules
+    let mut listings: Vec<(Source, Vec<UnifiedItem>)> = Vec::new();
+    let mut deleted_ids: HashSet<String> = HashSet::new();
+    let mut reports = Vec::new();
+
+    for rule in &policy.rules {
+        println!("\n=== Rule: {} ===", rule.name);
+        let mut items = Vec::new();
+        let mut seen_ids: HashSet<String> = HashSet::new();
+        for source in &rule.sources {
+            if !listings.iter().any(|(fetched, _)| fetched == source) {
+                let fetched =
+                    fetch_user_items(api, api.username(), &[*source], true, true, &ItemFilters::default(), false, None, debug_mode)
+                        .await?;
+                if debug_mode {
+                    println!("Fetched {} {} items.", fetched.len(), source.as_str());
+                }
+                listings.push((*source, fetched));
+            }
+            let (_, listing) = listings.iter().find(|(fetched, _)| fetched == source).expect("listing was just fetched");
+            items.extend(
+                listing
+                    .iter()
+                    .filter(|item| !deleted_ids.contains(&item.id) && rule.filters.matches(item))
+                    .filter(|item| seen_ids.insert(item.id.clone()))
+                    .cloned(),
+            );
+        }
+        items.sort_by(|a, b| b.created_utc.partial_cmp(&a.created_utc).unwrap_or(std::cmp::Ordering::Equal));
+        println!("{} items match.", items.len());
+
+        let (action, outcome) = match &rule.action {
+            RuleAction::Overwrite(text) => {
+                let overwritten = handle_overwrite_action(api, &mut items, text, ctx).await?;
+                ("overwrite", if ctx.dry_run { "dry run".to_string() } else { format!("{} overwritten", overwritten) })
+            }
+            RuleAction::Delete => {
+                let deleted = handle_delete_action(api, &items, ctx).await?;
+                let outcome = if ctx.dry_run { "dry run".to_string() } else { format!("{} deleted", deleted.len()) };
+                // Later rules must not act on items this rule deleted
+                deleted_ids.extend(deleted);
+                ("delete", outcome)
+            }
+            RuleAction::Export { output, format } => {
+                match format {
+                    OutputFormat::Csv => handle_csv_export(&items, output, debug_mode)?,
+                    _ => handle_json_export(&items, *format, Some(output), debug_mode)?,
+                }
+                ("export", format!("exported to {}", output))
+            }
+        };
+        reports.push(RuleReport { name: rule.name.clone(), action, matched: items.len(), outcome });
+    }
+
+    println!("\n--- Policy Report ---");
+    for report in &reports {
+        println!("  {:<32}{:<11}{:>6} matched   {}", report.name, report.action, report.matched, report.outcome);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_rules_with_defaults() -> Result<(), Box<dyn Error>> {
+        let policy = Policy::parse(
+            r#"
+            [[rule]]
+            name = "old comments"
+            source = ["comments"]
+            subreddit = ["rust", "golang"]
+            min_age = "90 days"
+            action = "delete"
+
+            [[rule]]
+            name = "old posts"
+            min_age = "1 year"
+            where = "score <= 500"
+            action = "export"
+            output = "old-posts.json"
+            format = "json"
+            "#,
+        )?;
+
+        assert_eq!(policy.rules.len(), 2);
+        assert_eq!(policy.rules[0].sources, vec![Source::Comments]);
+        assert_eq!(policy.rules[1].sources, vec![Source::Submitted, Source::Comments]);
+        assert!(matches!(&policy.rules[1].action, RuleAction::Export { format: OutputFormat::Json, .. }));
+        assert!(policy.modifies_items());
+
+        let mut item = UnifiedItem::from_fullname("t1_abc");
+        item.subreddit = "Rust".to_string();
+        item.created_utc = 1_000_000_000.0;
+        assert!(policy.rules[0].filters.matches(&item));
+        item.subreddit = "python".to_string();
+        assert!(!policy.rules[0].filters.matches(&item));
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_rules_name_the_rule() {
+        let error = Policy::parse("[[rule]]\nname = \"scrub\"\naction = \"overwrite\"\n").unwrap_err();
+        assert_eq!(error.to_string(), "rule \"scrub\": `action = \"overwrite\"` needs `text`");
+
+        let error = Policy::parse("[[rule]]\nname = \"x\"\nsource = [\"karma\"]\naction = \"delete\"\n").unwrap_err();
+        assert!(error.to_string().starts_with("rule \"x\": unknown source `karma`"));
+
+        let error = Policy::parse("[[rule]]\nname = \"x\"\naction = \"delete\"\nmin_score = 1\nmax_age = \"soon\"\n").unwrap_err();
+        assert!(error.to_string().starts_with("rule \"x\": Could not parse 'soon'"));
+
+        assert!(Policy::parse("[[rule]]\nname = \"x\"\naction = \"delete\"\nsubreddits = [\"rust\"]\n").is_err());
+        assert_eq!(Policy::parse("").unwrap_err().to_string(), "no [[rule]] entries");
+    }
+}
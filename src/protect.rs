@@ -0,0 +1,183 @@
+// Items that must never be overwritten, deleted or otherwise changed.
+//
+// The protect list file (`protect.txt` in the config directory, or `--protect-file`) holds one
+// entry per line:
+//   t1_abc123                                   a fullname
+//   https://www.reddit.com/r/rust/comments/...  a permalink (with or without the host)
+//   r/AskHistorians                             every item in a subreddit
+//   regex:(?i)wedding                           a regular expression on the body or title
+// Blank lines and lines starting with `#` are ignored. `--protect-score-above` and
+// `--protect-gilded` add built-in rules. Protection is checked by every action after all
+// filters have been applied; protected items are still exported.
+use crate::models::UnifiedItem;
+use regex::{Regex, RegexBuilder};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+pub const PROTECT_FILE: &str = "protect.txt";
+
+#[derive(Debug, Default)]
+pub struct ProtectList {
+    fullnames: HashSet<String>,
+    permalinks: HashSet<String>,
+    subreddits: HashSet<String>,
+    patterns: Vec<Regex>,
+    /// Keep items with a score above this.
+    pub score_above: Option<i32>,
+    /// Keep items that were awarded gold.
+    pub gilded: bool,
+}
+
+impl ProtectList {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read protect list '{}': {}", path.display(), e))?;
+        Self::parse(&contents).map_err(|e| format!("Invalid protect list '{}': {}", path.display(), e).into())
+    }
+
+    fn parse(contents: &str) -> Result<Self, String> {
+        let mut list = ProtectList::default();
+        for (index, line) in contents.lines().enumerate() {
+            let entry = line.trim();
+            if entry.is_empty() || entry.starts_with('#') {
+                continue;
+            }
+            if let Some(pattern) = entry.strip_prefix("regex:") {
+                let regex = RegexBuilder::new(pattern.trim())
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| format!("line {}: invalid regular expression: {}", index + 1, e))?;
+                list.patterns.push(regex);
+            } else if let Some(subreddit) = subreddit_entry(entry) {
+                list.subreddits.insert(subreddit);
+            } else if entry.starts_with('/') || entry.starts_with("http") {
+                list.permalinks.insert(normalize_permalink(entry));
+            } else if is_fullname(entry) {
+                list.fullnames.insert(entry.to_lowercase());
+            } else {
+                return Err(format!(
+                    "line {}: expected a fullname (t1_...), a permalink, r/<subreddit> or regex:<pattern>, found `{}`",
+                    index + 1,
+                    entry
+                ));
+            }
+        }
+        Ok(list)
+    }
+
+    /// Whether any rule is set, so summaries only mention protection when it is in use.
+    pub fn is_empty(&self) -> bool {
+        self.fullnames.is_empty()
+            && self.permalinks.is_empty()
+            && self.subreddits.is_empty()
+            && self.patterns.is_empty()
+            && self.score_above.is_none()
+            && !self.gilded
+    }
+
+    /// Why `item` is protected, or `None` if it may be changed.
+    pub fn reason(&self, item: &UnifiedItem) -> Option<String> {
+        if self.fullnames.contains(&item.id.to_lowercase()) {
+            return Some("listed in the protect list".to_string());
+        }
+        if !item.permalink.is_empty() && self.permalinks.contains(&normalize_permalink(&item.permalink)) {
+            return Some("permalink listed in the protect list".to_string());
+        }
+        if self.subreddits.contains(&item.subreddit.to_lowercase()) {
+            return Some(format!("r/{} is protected", item.subreddit));
+        }
+        if let Some(regex) = self.patterns.iter().find(|regex| regex.is_match(&item.content) || regex.is_match(&item.title)) {
+            return Some(format!("matches protected pattern `{}`", regex.as_str()));
+        }
+        if let Some(threshold) = self.score_above.filter(|threshold| item.upvotes > *threshold) {
+            return Some(format!("score {} is above {}", item.upvotes, threshold));
+        }
+        if self.gilded && item.gilded > 0 {
+            return Some("gilded".to_string());
+        }
+        None
+    }
+}
+
+/// `r/name` or `/r/name`, lowercased without the prefix.
+fn subreddit_entry(entry: &str) -> Option<String> {
+    let name = entry.trim_start_matches('/').trim_end_matches('/');
+    let name = name.strip_prefix("r/").or_else(|| name.strip_prefix("R/"))?;
+    (!name.is_empty() && !name.contains('/')).then(|| name.to_lowercase())
+}
+
+/// Strip the scheme, host and trailing slash so full URLs and relative permalinks compare equal.
+fn normalize_permalink(link: &str) -> String {
+    let path = match link.find("://") {
+        Some(scheme_end) => {
+            let rest = &link[scheme_end + 3..];
+            rest.find('/').map_or("", |path_start| &rest[path_start..])
+        }
+        None => link,
+    };
+    path.trim_end_matches('/').to_lowercase()
+}
+
+fn is_fullname(entry: &str) -> bool {
+    let mut chars = entry.chars();
+    chars.next().is_some_and(|c| c.eq_ignore_ascii_case(&'t'))
+        && chars.next().is_some_and(|c| ('1'..='6').contains(&c))
+        && chars.next() == Some('_')
+        && entry.len() > 3
+        && entry[3..].chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &str, subreddit: &str, upvotes: i32) -> UnifiedItem {
+        let mut item = UnifiedItem::from_fullname(id);
+        item.subreddit = subreddit.to_string();
+        item.upvotes = upvotes;
+        item.permalink = format!("/r/{}/comments/xyz/some_post/{}/", subreddit, &id[3..]);
+        item
+    }
+
+    #[test]
+    fn entries_of_every_kind_protect_items() -> Result<(), String> {
+        let list = ProtectList::parse(concat!(
+            "# keep these\n",
+            "t1_keep1\n",
+            "https://www.reddit.com/r/rust/comments/xyz/some_post/keep2\n",
+            "r/AskHistorians\n",
+            "\n",
+            "regex:wedding\n",
+        ))?;
+
+        assert_eq!(list.reason(&item("t1_keep1", "golang", 1)).as_deref(), Some("listed in the protect list"));
+        assert_eq!(list.reason(&item("t1_keep2", "rust", 1)).as_deref(), Some("permalink listed in the protect list"));
+        assert_eq!(list.reason(&item("t1_other", "askhistorians", 1)).as_deref(), Some("r/askhistorians is protected"));
+        let mut wedding = item("t3_post", "pics", 1);
+        wedding.title = "Our Wedding".to_string();
+        assert!(list.reason(&wedding).is_some());
+        assert_eq!(list.reason(&item("t1_other", "rust", 1)), None);
+        Ok(())
+    }
+
+    #[test]
+    fn built_in_rules() {
+        let list = ProtectList { score_above: Some(100), gilded: true, ..Default::default() };
+        assert!(!list.is_empty());
+        assert_eq!(list.reason(&item("t1_a", "rust", 101)).as_deref(), Some("score 101 is above 100"));
+        assert_eq!(list.reason(&item("t1_a", "rust", 100)), None);
+
+        let mut gilded = item("t1_a", "rust", 1);
+        gilded.gilded = 2;
+        assert_eq!(list.reason(&gilded).as_deref(), Some("gilded"));
+    }
+
+    #[test]
+    fn unknown_entries_are_rejected_with_their_line() {
+        let error = ProtectList::parse("t1_ok\nsomething else\n").unwrap_err();
+        assert!(error.starts_with("line 2: expected a fullname"));
+        assert!(ProtectList::parse("regex:(").unwrap_err().starts_with("line 1: invalid regular expression"));
+    }
+}
@@ -1,279 +1,618 @@
-use roux::Reddit;
-use roux::Me;
 use roux::util::FeedOption;
-use roux::{Submissions, Comments};
+use roux::comment::CommentData;
+use roux::response::{BasicThing, Listing};
+use roux::submission::SubmissionData;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
-use crate::config::Config; 
-use crate::models::UnifiedItem;
+use crate::api::RedditApi;
+use crate::auth::{load_refresh_token, refresh_token_path, Authenticator, Credentials, OneTimePassword};
+use crate::totp::decode_secret;
+use crate::cli::Source;
+use crate::config::Config;
+use crate::filters::ItemFilters;
+use crate::models::{ItemFlags, UnifiedItem};
 
-pub async fn connect_reddit(config: &Config, debug_mode: bool) -> Result<Me, Box<dyn Error>> {
+/// Number of items requested per listing page (Reddit's maximum).
+const PAGE_SIZE: u32 = 100;
+
+/// Reddit stops serving listing pages after roughly this many items.
+const LISTING_ITEM_CAP: usize = 1000;
+
+/// How close to `LISTING_ITEM_CAP` a finished listing must get before we warn about truncation.
+/// Removed and deleted items leave gaps, so a capped listing rarely returns exactly 1000 entries.
+const LISTING_CAP_MARGIN: usize = 25;
+
+/// Maximum number of fullnames `api/info` accepts per request.
+const INFO_BATCH_SIZE: usize = 100;
+
+/// A comment as returned by Reddit, with the `edited` marker roux's `CommentData` lacks.
+#[derive(Deserialize, Debug)]
+struct CommentThing {
+    #[serde(flatten)]
+    data: CommentData,
+    #[serde(default)]
+    edited: serde_json::Value,
+}
+
+/// A post or comment from a listing that mixes both (saved, upvoted, `api/info`, ...),
+/// read according to its `kind`. Any other kind fails to deserialize.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "kind", content = "data")]
+enum MixedThing {
+    #[serde(rename = "t3")]
+    Submission(SubmissionData),
+    #[serde(rename = "t1")]
+    Comment(CommentThing),
+}
+
+/// The fields of a private message (`t4_`) used by Bydit.
+#[derive(Deserialize, Debug)]
+struct MessageData {
+    name: String,
+    #[serde(default)]
+    author: Option<String>,
+    #[serde(default)]
+    dest: Option<String>,
+    #[serde(default)]
+    subject: String,
+    #[serde(default)]
+    body: String,
+    #[serde(default)]
+    subreddit: Option<String>,
+    #[serde(default)]
+    created_utc: f64,
+}
+
+/// A sort order (and optional time window) under which a user listing can be walked.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct ListingSort {
+    sort: &'static str,
+    time: Option<&'static str>,
+}
+
+impl ListingSort {
+    const NEW: ListingSort = ListingSort { sort: "new", time: None };
+
+    fn query(&self) -> String {
+        match self.time {
+            Some(time) => format!("sort={}&t={}", self.sort, time),
+            None => format!("sort={}", self.sort),
+        }
+    }
+
+    fn label(&self) -> String {
+        match self.time {
+            Some(time) => format!("{}/{}", self.sort, time),
+            None => self.sort.to_string(),
+        }
+    }
+}
+
+/// Every sort order walked by `--all-sorts`. Each one is capped at ~1000 items by Reddit,
+/// but they surface different slices of the history, so together they reach further back.
+const ALL_SORTS: &[ListingSort] = &[
+    ListingSort::NEW,
+    ListingSort { sort: "top", time: Some("hour") },
+    ListingSort { sort: "top", time: Some("day") },
+    ListingSort { sort: "top", time: Some("week") },
+    ListingSort { sort: "top", time: Some("month") },
+    ListingSort { sort: "top", time: Some("year") },
+    ListingSort { sort: "top", time: Some("all") },
+    ListingSort { sort: "controversial", time: Some("all") },
+    ListingSort { sort: "hot", time: None },
+];
+
+/// The part of `api/v1/me` needed to learn whose refresh token is in use.
+#[derive(Deserialize)]
+struct Identity {
+    name: String,
+}
+
+/// Log in with the refresh token stored by `bydit login`, falling back to the password grant
+/// when no token is stored and `config.toml` has a password. For accounts with two-factor
+/// authentication the code is generated from `totp_secret` or, with `prompt_otp`, asked for.
+pub async fn connect_reddit(config: &Config, prompt_otp: bool, debug_mode: bool) -> Result<RedditApi, Box<dyn Error>> {
     if debug_mode { println!("Connecting to Reddit and logging in..."); }
-    let reddit_client = Reddit::new(
-        &config.user_agent,
-        &config.client_id,
-        &config.client_secret,
-    )
-    .username(&config.username)
-    .password(&config.password)
-    .login()
-    .await
-    .map_err(|e| {
+    let stored_token = match refresh_token_path(config.profile.as_deref()) {
+        Some(path) if config.refresh_token.is_none() => load_refresh_token(&path)?.map(|token| (path, token)),
+        _ => None,
+    };
+
+    let credentials = if let Some(refresh_token) = &config.refresh_token {
+        Credentials::RefreshToken(refresh_token.clone())
+    } else if let Some((path, refresh_token)) = stored_token {
+        if debug_mode { println!("Using refresh token from {}", path.display()); }
+        Credentials::RefreshToken(refresh_token)
+    } else if !config.password.is_empty() {
+        if debug_mode { println!("No stored refresh token, using the password grant for {}", config.username); }
+        let otp = if let Some(secret) = &config.totp_secret {
+            if debug_mode { println!("Generating two-factor codes from totp_secret."); }
+            OneTimePassword::Totp(decode_secret(secret)?)
+        } else if prompt_otp {
+            OneTimePassword::Prompt
+        } else {
+            OneTimePassword::None
+        };
+        Credentials::Password { username: config.username.clone(), password: config.password.clone(), otp }
+    } else {
+        return Err("Not logged in: run `bydit login`, or set username and password in config.toml".into());
+    };
+    let authenticator = Authenticator::new(config, credentials);
+
+    let token = authenticator.fetch_token().await.map_err(|e| {
         if debug_mode { eprintln!("Failed to log in to Reddit: {}", e); }
-        Box::new(e) as Box<dyn Error>
+        e
     })?;
-    if debug_mode { println!("Successfully logged in to Reddit."); }
-    Ok(reddit_client)
+    if debug_mode { println!("Successfully logged in to Reddit (access token valid for {}s).", token.expires_in); }
+
+    let uses_refresh_token = authenticator.uses_refresh_token();
+    // With a refresh token, the account is whichever one authorized it
+    let mut api = RedditApi::new(&config.user_agent, &config.username, authenticator, token, config.retry.clone(), debug_mode)?;
+    if uses_refresh_token {
+        let identity: Identity = api.get_json("api/v1/me").await?;
+        api.set_username(&identity.name);
+    }
+    Ok(api)
+}
+
+/// Follow the `after` cursor of a user listing (e.g. `user/<name>/comments`) until Reddit
+/// reports no further pages, or until `max_pages` pages have been fetched.
+///
+/// `T` is one child of the listing (usually a `BasicThing`). Returns the fetched children
+/// and whether the listing ended at Reddit's item cap.
+async fn fetch_listing<T: DeserializeOwned>(
+    api: &RedditApi,
+    listing_path: &str,
+    sort: ListingSort,
+    label: &str,
+    max_pages: Option<u32>,
+    debug_mode: bool,
+) -> Result<(Vec<T>, bool), Box<dyn Error>> {
+    let mut all_fetched = Vec::new();
+    let mut after_token: Option<String> = None;
+    let mut page_count: u32 = 0;
+    let mut reached_end = false;
+
+    loop {
+        if max_pages.is_some_and(|cap| page_count >= cap) {
+            if debug_mode {
+                println!("Stopping after {} page(s) of {} (--max-pages).", page_count, label);
+            }
+            break;
+        }
+
+        page_count += 1;
+        if debug_mode {
+            println!("Fetching page {} of {} ({})...", page_count, label, sort.label());
+        }
+
+        // Build OAuth URL with sort and pagination options
+        let mut url = format!("{}/.json?{}", listing_path, sort.query());
+        let mut feed_options = FeedOption::new().limit(PAGE_SIZE);
+        if let Some(token) = &after_token {
+            feed_options = feed_options.after(token);
+        }
+        feed_options.build_url(&mut url);
+
+        let feed: BasicThing<Listing<T>> = api.get_json(&url).await.map_err(|e| {
+            if debug_mode {
+                eprintln!("\nError fetching page {} of {}: {}", page_count, label, e);
+            }
+            e
+        })?;
+
+        let num_fetched_this_page = feed.data.children.len();
+        if debug_mode {
+            println!("Fetched {} {} on page {}.", num_fetched_this_page, label, page_count);
+        }
+
+        all_fetched.extend(feed.data.children);
+        after_token = feed.data.after;
+
+        if after_token.is_none() {
+            if debug_mode {
+                println!("No more {} to fetch (after_token is None).", label);
+            }
+            reached_end = true;
+            break;
+        }
+        if num_fetched_this_page == 0 && page_count > 1 {
+            if debug_mode {
+                println!("Fetched 0 {} on page {} (not first page), assuming end of {}.", label, page_count, label);
+            }
+            reached_end = true;
+            break;
+        }
+    }
+
+    let hit_cap = reached_end && all_fetched.len() >= LISTING_ITEM_CAP - LISTING_CAP_MARGIN;
+    Ok((all_fetched, hit_cap))
+}
+
+/// Fetch one user listing and convert it to `UnifiedItem`s.
+///
+/// With `all_sorts` the listing is walked under every entry of `ALL_SORTS` and the results
+/// are de-duplicated by fullname; otherwise only the default `new` order is fetched.
+async fn fetch_listing_items<T: DeserializeOwned>(
+    api: &RedditApi,
+    listing_path: &str,
+    label: &str,
+    convert: fn(T) -> UnifiedItem,
+    all_sorts: bool,
+    max_pages: Option<u32>,
+    debug_mode: bool,
+) -> Result<Vec<UnifiedItem>, Box<dyn Error>> {
+    let sorts: &[ListingSort] = if all_sorts { ALL_SORTS } else { &[ListingSort::NEW] };
+    let mut seen_ids: HashSet<String> = HashSet::new();
+    let mut items = Vec::new();
+
+    for sort in sorts {
+        let (fetched, hit_cap): (Vec<T>, bool) =
+            fetch_listing(api, listing_path, *sort, label, max_pages, debug_mode).await?;
+        let num_fetched = fetched.len();
+
+        let mut num_new = 0;
+        for thing in fetched {
+            let item = convert(thing);
+            if seen_ids.insert(item.id.clone()) {
+                items.push(item);
+                num_new += 1;
+            }
+        }
+
+        if all_sorts {
+            eprintln!(
+                "  {:<20} fetched {:>5} {}, {:>5} new unique{}",
+                sort.label(),
+                num_fetched,
+                label,
+                num_new,
+                if hit_cap { " (listing limit reached)" } else { "" }
+            );
+        } else if hit_cap {
+            eprintln!(
+                "Warning: fetched {} {}, which is Reddit's listing limit of about {} items. Older {} exist but cannot be retrieved from this listing (try --all-sorts).",
+                num_fetched,
+                label,
+                LISTING_ITEM_CAP,
+                label
+            );
+        }
+    }
+
+    if all_sorts {
+        eprintln!("Discovered {} unique {} across {} sort orders.", items.len(), label, sorts.len());
+    }
+
+    Ok(items)
+}
+
+/// Reddit reports `edited` as `false` or the time of the last edit.
+fn is_edited(edited: &serde_json::Value) -> bool {
+    match edited {
+        serde_json::Value::Bool(edited) => *edited,
+        serde_json::Value::Number(_) => true,
+        _ => false,
+    }
+}
+
+fn post_to_item(post: SubmissionData) -> UnifiedItem {
+    let flags = ItemFlags {
+        edited: is_edited(&post.edited),
+        stickied: post.stickied,
+        locked: post.locked,
+        over_18: post.over_18,
+        spoiler: post.spoiler,
+        archived: post.archived,
+        distinguished: post.distinguished.clone().unwrap_or_default(),
+    };
+    UnifiedItem {
+        id: post.name,
+        item_type: "Post".to_string(),
+        subreddit: post.subreddit,
+        title: post.title,
+        content: post.selftext,
+        upvotes: post.ups as i32,
+        num_comments: post.num_comments as i32,
+        permalink: post.permalink,
+        created_utc: post.created_utc,
+        source: Source::Submitted.as_str().to_string(),
+        author: String::new(),
+        recipient: String::new(),
+        gilded: post.gilded as i32,
+        flags,
+    }
+}
+
+fn comment_to_item(comment: CommentThing) -> UnifiedItem {
+    let flags = ItemFlags {
+        edited: is_edited(&comment.edited),
+        stickied: comment.data.stickied.unwrap_or(false),
+        locked: comment.data.locked.unwrap_or(false),
+        over_18: comment.data.over_18.unwrap_or(false),
+        spoiler: false,
+        archived: comment.data.archived.unwrap_or(false),
+        distinguished: comment.data.distinguished.clone().unwrap_or_default(),
+    };
+    let comment = comment.data;
+    UnifiedItem {
+        id: comment.name.unwrap_or_default(),
+        item_type: "Comment".to_string(),
+        subreddit: comment.subreddit.unwrap_or_default(),
+        title: comment.link_title.unwrap_or_default(),
+        content: comment.body.unwrap_or_default(),
+        upvotes: comment.score.unwrap_or(0),
+        num_comments: 0, // Comments don't have a direct num_comments field in this context
+        permalink: comment.permalink.unwrap_or_default(),
+        created_utc: comment.created_utc.unwrap_or(0.0),
+        source: Source::Comments.as_str().to_string(),
+        author: String::new(),
+        recipient: String::new(),
+        gilded: comment.gilded.unwrap_or(0),
+        flags,
+    }
 }
 
+fn message_to_item(message: MessageData) -> UnifiedItem {
+    let permalink = format!("/message/messages/{}", message.name.trim_start_matches("t4_"));
+    UnifiedItem {
+        id: message.name,
+        item_type: "Message".to_string(),
+        subreddit: message.subreddit.unwrap_or_default(),
+        title: message.subject,
+        content: message.body,
+        upvotes: 0,
+        num_comments: 0,
+        permalink,
+        created_utc: message.created_utc,
+        source: Source::Messages.as_str().to_string(),
+        author: message.author.unwrap_or_default(),
+        recipient: message.dest.unwrap_or_default(),
+        gilded: 0,
+        flags: ItemFlags::default(),
+    }
+}
+
+/// Path of a listing of the authenticated user, relative to the OAuth base URL.
+fn listing_path(source: Source, username: &str) -> String {
+    if source.is_message() {
+        format!("message/{}", source.as_str())
+    } else {
+        format!("user/{}/{}", username, source.as_str())
+    }
+}
+
+fn saved_to_item(thing: MixedThing) -> UnifiedItem {
+    match thing {
+        MixedThing::Submission(post) => post_to_item(post),
+        MixedThing::Comment(comment) => comment_to_item(comment),
+    }
+}
+
+/// Fetch the given listings of the authenticated user. `include_posts`/`include_comments`
+/// select the item types kept from listings that mix both (saved, upvoted, ...).
+/// An item found in several listings is kept once, tagged with the first listing it came from.
+#[allow(clippy::too_many_arguments)]
 pub async fn fetch_user_items(
-    reddit: &Me,
+    api: &RedditApi,
     username: &str,
-    do_fetch_posts: bool,
-    do_fetch_comments: bool,
-    filter_subreddit: Option<&String>,
-    exclude_subreddit: Option<&String>,
-    filter_score: Option<i32>,
-    filter_max_score: Option<i32>,
-    min_age_timestamp: Option<f64>,
-    max_age_timestamp: Option<f64>,
-    filter_post_title: Option<&String>,
+    sources: &[Source],
+    include_posts: bool,
+    include_comments: bool,
+    filters: &ItemFilters,
+    all_sorts: bool,
+    max_pages: Option<u32>,
     debug_mode: bool,
 ) -> Result<Vec<UnifiedItem>, Box<dyn Error>> {
     let mut all_items: Vec<UnifiedItem> = Vec::new();
+    let retries_before = api.retries();
 
-    // Parse comma-separated subreddit lists
-    let include_subreddits: Option<Vec<String>> = filter_subreddit.map(|s| {
-        s.split(',')
-            .map(|sr| sr.trim().to_lowercase())
-            .filter(|sr| !sr.is_empty())
-            .collect()
-    });
-
-    let exclude_subreddits: Option<Vec<String>> = exclude_subreddit.map(|s| {
-        s.split(',')
-            .map(|sr| sr.trim().to_lowercase())
-            .filter(|sr| !sr.is_empty())
-            .collect()
-    });
-
-    if do_fetch_posts {
+    if include_posts && sources.contains(&Source::Submitted) {
         if debug_mode {
             println!("Fetching your posts...");
         }
-        // Use authenticated OAuth client to fetch user's submitted posts
-        let mut url = format!("user/{}/submitted/.json?", username);
-        let feed_options = FeedOption::new().limit(100);
-        feed_options.build_url(&mut url);
-        let oauth_url = roux::util::url::build_oauth(&url);
-
-        if debug_mode { println!("GET {}", oauth_url); }
-        let submitted_feed_res = reddit.client.get(&oauth_url).send().await;
-        match submitted_feed_res {
-            Ok(response) => {
-                if debug_mode { println!("Status: {}", response.status()); }
-                let submitted_feed: Submissions = response.json().await.map_err(|e| {
-                    if debug_mode {
-                        eprintln!("Failed to parse submitted posts JSON: {}", e);
-                    }
-                    Box::new(e) as Box<dyn Error>
-                })?;
-
-                let filtered_posts: Vec<_> = submitted_feed
-                    .data
-                    .children
-                    .into_iter()
-                    .filter(|post| {
-                        let post_subreddit = post.data.subreddit.to_lowercase();
-                        
-                        // Check if subreddit is in include list (if specified)
-                        let include_match = include_subreddits.as_ref().map_or(true, |list| {
-                            list.iter().any(|sr| sr == &post_subreddit)
-                        });
-                        
-                        // Check if subreddit is NOT in exclude list (if specified)
-                        let exclude_match = exclude_subreddits.as_ref().map_or(true, |list| {
-                            !list.iter().any(|sr| sr == &post_subreddit)
-                        });
-                        
-                        let score_match = filter_score.map_or(true, |s| post.data.ups >= s as f64);
-                        let max_score_match = filter_max_score.map_or(true, |s| post.data.ups < s as f64);
-                        
-                        // Age filtering: created_utc is the timestamp when the post was created
-                        // min_age_timestamp: items must be OLDER than this (created_utc <= min_age_timestamp)
-                        // max_age_timestamp: items must be NEWER than this (created_utc >= max_age_timestamp)
-                        let min_age_match = min_age_timestamp.map_or(true, |min_ts| post.data.created_utc <= min_ts);
-                        let max_age_match = max_age_timestamp.map_or(true, |max_ts| post.data.created_utc >= max_ts);
-                        
-                        include_match && exclude_match && score_match && max_score_match && min_age_match && max_age_match
-                    })
-                    .collect();
-
-                let num_filtered_posts = filtered_posts.len();
-                for post_data in filtered_posts.into_iter() {
-                    let item = UnifiedItem {
-                        id: post_data.data.name.clone(),
-                        item_type: "Post".to_string(),
-                        subreddit: post_data.data.subreddit,
-                        title: post_data.data.title,
-                        content: post_data.data.selftext,
-                        upvotes: post_data.data.ups as i32,
-                        num_comments: post_data.data.num_comments as i32,
-                        permalink: post_data.data.permalink,
-                        created_utc: post_data.data.created_utc,
-                    };
-                    all_items.push(item);
-                }
-                if debug_mode {
-                    println!("Collected {} posts.", num_filtered_posts);
-                }
-            }
-            Err(e) => {
-                if debug_mode {
-                    eprintln!("\nFailed to fetch your submitted posts: {}", e);
-                }
-                return Err(Box::new(e) as Box<dyn Error>);
-            }
+        if all_sorts {
+            eprintln!("Walking all sort orders of your submitted posts...");
         }
-    }
+        let submitted_path = format!("user/{}/submitted", username);
+        let to_item = |thing: BasicThing<SubmissionData>| post_to_item(thing.data);
+        let fetched_posts =
+            fetch_listing_items(api, &submitted_path, "posts", to_item, all_sorts, max_pages, debug_mode).await?;
 
-    if do_fetch_comments {
         if debug_mode {
-            println!("\nFetching your comments...");
+            println!("Finished fetching all post pages. Total raw posts fetched: {}", fetched_posts.len());
         }
-        let mut all_fetched_comments = Vec::new();
-        let mut after_token: Option<String> = None;
-        let mut page_count = 0;
 
-        loop {
-            page_count += 1;
-            if debug_mode {
-                println!("Fetching page {} of comments...", page_count);
-            }
+        let num_before = all_items.len();
+        all_items.extend(fetched_posts.into_iter().filter(|item| filters.matches(item)));
+        if debug_mode {
+            println!("Collected {} posts after filtering.", all_items.len() - num_before);
+        }
+    }
 
-            // Build OAuth URL with pagination options
-            let mut url = format!("user/{}/comments/.json?", username);
-            let mut feed_options = FeedOption::new().limit(100);
-            if let Some(token) = &after_token {
-                feed_options = feed_options.after(token);
-            }
-            feed_options.build_url(&mut url);
-            let oauth_url = roux::util::url::build_oauth(&url);
-
-            if debug_mode { println!("GET {}", oauth_url); }
-            match reddit.client.get(&oauth_url).send().await {
-                Ok(response) => {
-                    if debug_mode { println!("Status: {}", response.status()); }
-                    let comments_feed: Comments = response.json().await.map_err(|e| {
-                        if debug_mode {
-                            eprintln!("Failed to parse comments JSON on page {}: {}", page_count, e);
-                        }
-                        Box::new(e) as Box<dyn Error>
-                    })?;
-
-                    let num_fetched_this_page = comments_feed.data.children.len();
-                    if debug_mode {
-                        println!("Fetched {} comments on page {}.", num_fetched_this_page, page_count);
-                    }
-
-                    all_fetched_comments.extend(comments_feed.data.children);
-                    after_token = comments_feed.data.after;
-
-                    if after_token.is_none() {
-                        if debug_mode {
-                            println!("No more comments to fetch (after_token is None).");
-                        }
-                        break;
-                    }
-                    if num_fetched_this_page == 0 && page_count > 1 {
-                        if debug_mode {
-                            println!("Fetched 0 comments on page {} (not first page), assuming end of comments.", page_count);
-                        }
-                        break;
-                    }
-                }
-                Err(e) => {
-                    if debug_mode {
-                        eprintln!("\nError fetching page {} of comments: {}", page_count, e);
-                    }
-                    return Err(Box::new(e) as Box<dyn Error>);
-                }
-            }
+    if include_comments && sources.contains(&Source::Comments) {
+        if debug_mode {
+            println!("\nFetching your comments...");
+        }
+        if all_sorts {
+            eprintln!("Walking all sort orders of your comments...");
         }
+        let comments_path = format!("user/{}/comments", username);
+        let to_item = |thing: BasicThing<CommentThing>| comment_to_item(thing.data);
+        let fetched_comments =
+            fetch_listing_items(api, &comments_path, "comments", to_item, all_sorts, max_pages, debug_mode).await?;
 
         if debug_mode {
-            println!("Finished fetching all comment pages. Total raw comments fetched: {}", all_fetched_comments.len());
+            println!("Finished fetching all comment pages. Total raw comments fetched: {}", fetched_comments.len());
         }
 
-        if all_fetched_comments.is_empty() {
+        if fetched_comments.is_empty() {
             if debug_mode {
                 println!("No comments found for this user after attempting to fetch all pages.");
             }
         } else {
-            let filtered_comments: Vec<_> = all_fetched_comments
+            let num_before = all_items.len();
+            all_items.extend(fetched_comments.into_iter().filter(|item| filters.matches(item)));
+            if debug_mode {
+                println!("Collected {} comments after filtering.", all_items.len() - num_before);
+            }
+        }
+    }
+
+    for source in sources.iter().filter(|source| !source.is_own_content()) {
+        let label = format!("{} items", source.as_str());
+        if debug_mode {
+            println!("\nFetching your {}...", label);
+        }
+        // These listings mix item types and are only walked in their default order
+        let listing_path = listing_path(*source, username);
+        let fetched_items = if source.is_message() {
+            // The inbox also lists comment replies (t1_), which are not private messages
+            let to_item = |thing: BasicThing<MessageData>| message_to_item(thing.data);
+            let mut messages =
+                fetch_listing_items(api, &listing_path, &label, to_item, false, max_pages, debug_mode).await?;
+            messages.retain(|item| item.id.starts_with("t4_"));
+            messages
+        } else {
+            fetch_listing_items(api, &listing_path, &label, saved_to_item, false, max_pages, debug_mode).await?
+        };
+
+        if debug_mode {
+            println!("Finished fetching all {} pages. Total raw items fetched: {}", source.as_str(), fetched_items.len());
+        }
+
+        let num_before = all_items.len();
+        all_items.extend(
+            fetched_items
                 .into_iter()
-                .filter(|comment| {
-                    let comment_subreddit = comment.data.subreddit.as_ref().map(|s| s.to_lowercase());
-                    
-                    // Check if subreddit is in include list (if specified)
-                    let include_match = include_subreddits.as_ref().map_or(true, |list| {
-                        comment_subreddit.as_ref().map_or(false, |cs| {
-                            list.iter().any(|sr| sr == cs)
-                        })
-                    });
-                    
-                    // Check if subreddit is NOT in exclude list (if specified)
-                    let exclude_match = exclude_subreddits.as_ref().map_or(true, |list| {
-                        comment_subreddit.as_ref().map_or(false, |cs| {
-                            !list.iter().any(|sr| sr == cs)
-                        })
-                    });
-                    
-                    let score_match = filter_score.map_or(true, |s_filter| {
-                        comment.data.score.map_or(true, |s_comment| s_comment >= s_filter)
-                    });
-                    let max_score_match = filter_max_score.map_or(true, |s_filter| {
-                        comment.data.score.map_or(true, |s_comment| s_comment < s_filter)
-                    });
-                    
-                    // Age filtering for comments (min_age => older than threshold, max_age => newer than threshold)
-                    let older_than_match = min_age_timestamp.map_or(true, |min_ts| {
-                        comment.data.created_utc.map_or(true, |created| created <= min_ts)
-                    });
-                    let newer_than_match = max_age_timestamp.map_or(true, |max_ts| {
-                        comment.data.created_utc.map_or(true, |created| created >= max_ts)
-                    });
-                    
-                    // Post title filtering for comments (case-insensitive substring match)
-                    let post_title_match = filter_post_title.as_ref().map_or(true, |filter_title| {
-                        comment.data.link_title.as_ref().map_or(false, |link_title| {
-                            link_title.to_lowercase().contains(&filter_title.to_lowercase())
-                        })
-                    });
-                    
-                    include_match && exclude_match && score_match && max_score_match && older_than_match && newer_than_match && post_title_match
+                .map(|mut item| {
+                    item.source = source.as_str().to_string();
+                    item
                 })
-                .collect();
-            
-            let num_filtered_comments = filtered_comments.len();
-            for comment_data in filtered_comments.into_iter() {
-                let item = UnifiedItem {
-                    id: comment_data.data.name.clone().unwrap_or_default(),
-                    item_type: "Comment".to_string(),
-                    subreddit: comment_data.data.subreddit.unwrap_or_default(),
-                    title: comment_data.data.link_title.unwrap_or_default(),
-                    content: comment_data.data.body.unwrap_or_default(),
-                    upvotes: comment_data.data.score.unwrap_or(0),
-                    num_comments: 0, // Comments don't have a direct num_comments field in this context
-                    permalink: comment_data.data.permalink.unwrap_or_default(),
-                    created_utc: comment_data.data.created_utc.unwrap_or(0.0),
-                };
-                all_items.push(item);
-            }
+                .filter(|item| match item.item_type.as_str() {
+                    "Post" => include_posts,
+                    "Comment" => include_comments,
+                    _ => true,
+                })
+                .filter(|item| filters.matches(item)),
+        );
+        if debug_mode {
+            println!("Collected {} {} after filtering.", all_items.len() - num_before, label);
+        }
+    }
+
+    let mut seen_ids: HashSet<String> = HashSet::new();
+    all_items.retain(|item| seen_ids.insert(item.id.clone()));
+
+    let num_retries = api.retries() - retries_before;
+    if num_retries > 0 {
+        eprintln!("Fetching needed {} retried request(s).", num_retries);
+    }
+
+    Ok(all_items)
+}
+
+/// Refresh items with their live data from `api/info`, looked up in batches by fullname.
+///
+/// Used for items that did not come from a listing (e.g. a data export), which lack scores,
+/// link titles and comment counts. Items Reddit no longer returns keep their existing fields.
+pub async fn hydrate_items(
+    api: &RedditApi,
+    items: &mut [UnifiedItem],
+    debug_mode: bool,
+) -> Result<(), Box<dyn Error>> {
+    let mut hydrated: HashMap<String, UnifiedItem> = HashMap::new();
+    let num_batches = items.len().div_ceil(INFO_BATCH_SIZE);
+
+    for (batch_index, batch) in items.chunks(INFO_BATCH_SIZE).enumerate() {
+        let ids = batch.iter().map(|item| item.id.as_str()).collect::<Vec<_>>().join(",");
+        if debug_mode {
+            println!("Hydrating batch {}/{} ({} items)...", batch_index + 1, num_batches, batch.len());
+        }
+        let info: BasicThing<Listing<MixedThing>> = api.get_json(&format!("api/info/.json?id={}", ids)).await.map_err(|e| {
             if debug_mode {
-                println!("Collected {} comments after filtering.", num_filtered_comments);
+                eprintln!("\nError fetching api/info batch {}: {}", batch_index + 1, e);
             }
+            e
+        })?;
+
+        for thing in info.data.children {
+            let item = saved_to_item(thing);
+            hydrated.insert(item.id.clone(), item);
         }
     }
 
-    Ok(all_items)
+    let mut num_hydrated = 0;
+    for item in items.iter_mut() {
+        if let Some(mut live_item) = hydrated.remove(&item.id) {
+            live_item.source = std::mem::take(&mut item.source);
+            *item = live_item;
+            num_hydrated += 1;
+        }
+    }
+    if debug_mode {
+        println!(
+            "Hydrated {} of {} items ({} no longer returned by Reddit).",
+            num_hydrated,
+            items.len(),
+            items.len() - num_hydrated
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_map_to_message_items() -> Result<(), Box<dyn Error>> {
+        let message: MessageData = serde_json::from_str(
+            r#"{"name": "t4_2abc", "author": "alice", "dest": "bob", "subject": "Hi", "body": "Hello there",
+                "subreddit": null, "created_utc": 1705314600.0, "was_comment": false}"#,
+        )?;
+        let item = message_to_item(message);
+        assert_eq!(item.item_type, "Message");
+        assert_eq!((item.author.as_str(), item.recipient.as_str()), ("alice", "bob"));
+        assert_eq!((item.title.as_str(), item.content.as_str()), ("Hi", "Hello there"));
+        assert_eq!(item.permalink, "/message/messages/2abc");
+        Ok(())
+    }
+
+    #[test]
+    fn message_sources_live_under_message() {
+        assert_eq!(listing_path(Source::Sent, "someone"), "message/sent");
+        assert_eq!(listing_path(Source::Saved, "someone"), "user/someone/saved");
+    }
+
+    #[test]
+    fn comment_flags_are_carried_into_items() -> Result<(), Box<dyn Error>> {
+        let thing: MixedThing = serde_json::from_str(
+            r#"{"kind": "t1", "data": {"name": "t1_abc", "subreddit": "rust", "body": "old", "score": 3,
+                "created_utc": 1500000000.0, "edited": 1500000100.0, "archived": true, "locked": false,
+                "stickied": true, "distinguished": "moderator", "gilded": 1}}"#,
+        )?;
+        let item = saved_to_item(thing);
+        assert_eq!(item.item_type, "Comment");
+        assert!(item.flags.edited && item.flags.archived && item.flags.stickied);
+        assert!(!item.flags.locked && !item.flags.spoiler);
+        assert_eq!(item.flags.distinguished, "moderator");
+        assert_eq!(item.gilded, 1);
+
+        let json = serde_json::to_value(&item)?;
+        assert_eq!(json["archived"], true);
+        Ok(())
+    }
+
+    #[test]
+    fn mixed_things_are_read_by_kind() {
+        let thing: MixedThing = serde_json::from_str(r#"{"kind": "t1", "data": {"name": "t1_abc", "body": "hi"}}"#).unwrap();
+        assert_eq!(saved_to_item(thing).item_type, "Comment");
+
+        let error = serde_json::from_str::<MixedThing>(r#"{"kind": "t5", "data": {"name": "t5_abc"}}"#).unwrap_err();
+        assert!(error.to_string().contains("unknown variant `t5`"));
+    }
 }
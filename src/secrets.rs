@@ -0,0 +1,195 @@
+// Resolution of secret config values from outside `config.toml`.
+//
+// Each secret (`client_secret`, `password`, `totp_secret`, `refresh_token`) is looked up in
+// this order, the first hit wins:
+//   1. environment variable `BYDIT_<FIELD>` (`BYDIT_<PROFILE>_<FIELD>` for a named profile)
+//   2. `<field>_command` in the config, run through `sh -c`, printing the secret
+//   3. the Secret Service keyring via `secret-tool`, when `use_keyring = true`
+//   4. the plain value in the config file
+// The stored refresh token file of `bydit login` comes after all of these.
+use crate::config::Config;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::process::{Command, Stdio};
+
+/// Where a secret was found, reported with `--debug` instead of the value.
+#[derive(Debug)]
+enum SecretSource {
+    Environment(String),
+    Command,
+    Keyring,
+    ConfigFile,
+}
+
+impl fmt::Display for SecretSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretSource::Environment(var) => write!(f, "environment variable {}", var),
+            SecretSource::Command => write!(f, "command"),
+            SecretSource::Keyring => write!(f, "system keyring"),
+            SecretSource::ConfigFile => write!(f, "config file"),
+        }
+    }
+}
+
+type SecretResult<T> = Result<T, Box<dyn Error>>;
+
+/// Lookups that reach outside the process, replaceable in tests.
+struct SecretEnv {
+    var: fn(&str) -> Option<String>,
+    run: fn(&str) -> SecretResult<String>,
+    keyring: fn(&str, &str) -> SecretResult<Option<String>>,
+}
+
+const PROCESS_ENV: SecretEnv = SecretEnv {
+    var: |name| env::var(name).ok().filter(|value| !value.is_empty()),
+    run: run_secret_command,
+    keyring: keyring_lookup,
+};
+
+/// Fill every secret of `config` from the first source that provides it.
+pub fn resolve_secrets(config: &mut Config, debug_mode: bool) -> Result<(), Box<dyn Error>> {
+    resolve_secrets_with(config, &PROCESS_ENV, debug_mode)
+}
+
+fn resolve_secrets_with(config: &mut Config, secret_env: &SecretEnv, debug_mode: bool) -> Result<(), Box<dyn Error>> {
+    let client_secret = resolve(config, "client_secret", Some(&config.client_secret), config.client_secret_command.as_deref(), secret_env, debug_mode)?;
+    let password = resolve(config, "password", Some(&config.password), config.password_command.as_deref(), secret_env, debug_mode)?;
+    let totp_secret = resolve(config, "totp_secret", config.totp_secret.as_deref(), config.totp_secret_command.as_deref(), secret_env, debug_mode)?;
+    let refresh_token = resolve(config, "refresh_token", config.refresh_token.as_deref(), config.refresh_token_command.as_deref(), secret_env, debug_mode)?;
+
+    config.client_secret = client_secret.unwrap_or_default();
+    config.password = password.unwrap_or_default();
+    config.totp_secret = totp_secret;
+    config.refresh_token = refresh_token;
+    Ok(())
+}
+
+fn resolve(
+    config: &Config,
+    field: &str,
+    plain_value: Option<&str>,
+    command: Option<&str>,
+    secret_env: &SecretEnv,
+    debug_mode: bool,
+) -> Result<Option<String>, Box<dyn Error>> {
+    let var_name = env_var_name(config.profile.as_deref(), field);
+
+    let found = if let Some(value) = (secret_env.var)(&var_name) {
+        Some((value, SecretSource::Environment(var_name)))
+    } else if let Some(command) = command {
+        let value = (secret_env.run)(command).map_err(|e| format!("{}_command failed: {}", field, e))?;
+        Some((value, SecretSource::Command))
+    } else if let Some(value) = config
+        .use_keyring
+        .then(|| (secret_env.keyring)(config.profile_name(), field))
+        .transpose()?
+        .flatten()
+    {
+        Some((value, SecretSource::Keyring))
+    } else {
+        plain_value
+            .filter(|value| !value.is_empty())
+            .map(|value| (value.to_string(), SecretSource::ConfigFile))
+    };
+
+    if debug_mode {
+        match &found {
+            Some((_, source)) => println!("{}: from {}", field, source),
+            None => println!("{}: not set", field),
+        }
+    }
+    Ok(found.map(|(value, _)| value))
+}
+
+/// `BYDIT_PASSWORD`, or `BYDIT_WORK_PASSWORD` for profile `work`.
+fn env_var_name(profile: Option<&str>, field: &str) -> String {
+    let name = match profile {
+        Some(profile) => format!("BYDIT_{}_{}", profile, field),
+        None => format!("BYDIT_{}", field),
+    };
+    name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect()
+}
+
+fn run_secret_command(command: &str) -> Result<String, Box<dyn Error>> {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        return Err(format!("'{}' exited with {}", command, output.status).into());
+    }
+    let stdout = String::from_utf8(output.stdout)?;
+    // Like `pass show`, only the first line is the secret
+    let secret = stdout.lines().next().unwrap_or_default().trim().to_string();
+    if secret.is_empty() {
+        return Err(format!("'{}' printed nothing", command).into());
+    }
+    Ok(secret)
+}
+
+/// Look the secret up with `secret-tool lookup service bydit profile <profile> secret <field>`.
+fn keyring_lookup(profile: &str, field: &str) -> Result<Option<String>, Box<dyn Error>> {
+    let output = Command::new("secret-tool")
+        .args(["lookup", "service", "bydit", "profile", profile, "secret", field])
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|e| format!("use_keyring is set but secret-tool could not be run: {}", e))?;
+    // secret-tool exits with 1 when no matching entry exists
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let secret = String::from_utf8(output.stdout)?.trim().to_string();
+    Ok((!secret.is_empty()).then_some(secret))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(contents: &str) -> Config {
+        let base = "user_agent = \"ua\"\nclient_id = \"id\"\n";
+        toml::from_str(&format!("{}{}", base, contents)).unwrap()
+    }
+
+    const TEST_ENV: SecretEnv = SecretEnv {
+        var: |name| (name == "BYDIT_PASSWORD").then(|| "from-env".to_string()),
+        run: |command| Ok(format!("ran {}", command)),
+        keyring: |_, field| Ok((field == "refresh_token").then(|| "from-keyring".to_string())),
+    };
+
+    #[test]
+    fn sources_apply_in_precedence_order() -> Result<(), Box<dyn Error>> {
+        let mut config = config(concat!(
+            "client_secret = \"plain\"\nclient_secret_command = \"pass show reddit\"\n",
+            "password = \"plain\"\npassword_command = \"ignored\"\n",
+            "totp_secret = \"plain-totp\"\nuse_keyring = true\n",
+        ));
+        resolve_secrets_with(&mut config, &TEST_ENV, false)?;
+        assert_eq!(config.password, "from-env");
+        assert_eq!(config.client_secret, "ran pass show reddit");
+        assert_eq!(config.refresh_token.as_deref(), Some("from-keyring"));
+        assert_eq!(config.totp_secret.as_deref(), Some("plain-totp"));
+        Ok(())
+    }
+
+    #[test]
+    fn keyring_is_opt_in() -> Result<(), Box<dyn Error>> {
+        let mut config = config("");
+        resolve_secrets_with(&mut config, &TEST_ENV, false)?;
+        assert_eq!(config.refresh_token, None);
+        assert_eq!(config.client_secret, "");
+        Ok(())
+    }
+
+    #[test]
+    fn env_var_names_include_the_profile() {
+        assert_eq!(env_var_name(None, "client_secret"), "BYDIT_CLIENT_SECRET");
+        assert_eq!(env_var_name(Some("work-alt"), "password"), "BYDIT_WORK_ALT_PASSWORD");
+    }
+}
@@ -1,6 +1,7 @@
 // Utility functions
 use std::time::{SystemTime, UNIX_EPOCH};
 use std::error::Error;
+use std::path::Path;
 
 /// Parse an age string that can be either:
 /// - A humantime duration (e.g., "1 week", "2 years")
@@ -39,6 +40,26 @@ pub fn parse_age_to_timestamp(age_str: &str) -> Result<f64, Box<dyn Error>> {
     Err(format!("Could not parse '{}' as either a duration (e.g., '1 week') or a date (e.g., '2024-01-15')", age_str).into())
 }
 
+/// Format a Unix timestamp as ISO-8601 in UTC, e.g. `2024-01-15T10:30:00Z`.
+pub fn format_timestamp_iso(timestamp: f64) -> String {
+    use chrono::{DateTime, SecondsFormat};
+
+    DateTime::from_timestamp(timestamp as i64, 0)
+        .map(|dt| dt.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .unwrap_or_default()
+}
+
+/// Insert a profile name before the extension: `out.csv` becomes `out-work.csv`.
+pub fn profile_output_path(path: &str, profile: &str) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy()).unwrap_or_default();
+    let file_name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, profile, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, profile),
+    };
+    path.with_file_name(file_name).to_string_lossy().into_owned()
+}
+
 pub fn escape_csv_field(field: &str) -> String {
     field
         .replace("\r\n", "\\n") // Normalize all common line endings to \n
@@ -51,6 +72,18 @@ pub fn escape_csv_field(field: &str) -> String {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_profile_output_path() {
+        assert_eq!(profile_output_path("out.csv", "work"), "out-work.csv");
+        assert_eq!(profile_output_path("exports/items", "alt"), "exports/items-alt");
+    }
+
+    #[test]
+    fn test_format_timestamp_iso() {
+        assert_eq!(format_timestamp_iso(0.0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_timestamp_iso(1705314600.0), "2024-01-15T10:30:00Z");
+    }
+
     #[test]
     fn test_escape_empty_string() {
         assert_eq!(escape_csv_field(""), "");
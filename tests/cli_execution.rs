@@ -25,3 +25,138 @@ fn test_run_without_args_shows_help() -> Result<(), Box<dyn std::error::Error>>
     temp_dir.close()?;
     Ok(())
 }
+
+#[test]
+fn test_backups_search_reads_data_dir() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = tempdir()?;
+    let backup_dir = temp_dir.path().join("bydit");
+    std::fs::create_dir_all(&backup_dir)?;
+    std::fs::write(
+        backup_dir.join("backups.jsonl"),
+        concat!(
+            r#"{"backed_up_at":"2024-01-01T00:00:00Z","action":"delete","item":{"id":"t1_abc","item_type":"Comment","subreddit":"rust","title":"A post","content":"I used to work at ACME","upvotes":3,"num_comments":0,"permalink":"/r/rust/comments/x/y/abc/","created_utc":1700000000.0}}"#,
+            "\n"
+        ),
+    )?;
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(temp_dir.path())
+        .env("XDG_DATA_HOME", temp_dir.path())
+        .args(["backups", "search", "acme"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("t1_abc").and(predicate::str::contains("I used to work at ACME")));
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(temp_dir.path())
+        .env("XDG_DATA_HOME", temp_dir.path())
+        .args(["backups", "search", "golang"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("No backups found."));
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_strict_permissions_and_fix_permissions() -> Result<(), Box<dyn std::error::Error>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp_dir = tempdir()?;
+    let config_path = temp_dir.path().join("config.toml");
+    std::fs::write(&config_path, "user_agent = \"ua\"\nclient_id = \"id\"\n")?;
+    std::fs::set_permissions(&config_path, std::fs::Permissions::from_mode(0o644))?;
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(temp_dir.path())
+        .arg("--strict-permissions")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is readable by other users (mode 0644)"));
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(temp_dir.path())
+        .args(["config", "fix-permissions"])
+        .assert()
+        .success();
+    assert_eq!(std::fs::metadata(&config_path)?.permissions().mode() & 0o777, 0o600);
+    Ok(())
+}
+
+#[test]
+fn test_init_keeps_existing_config() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = tempdir()?;
+    let config_path = temp_dir.path().join("bydit").join("config.toml");
+    std::fs::create_dir_all(config_path.parent().unwrap())?;
+    std::fs::write(&config_path, "user_agent = \"ua\"\n")?;
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(temp_dir.path())
+        .env("XDG_CONFIG_HOME", temp_dir.path())
+        .arg("init")
+        .write_stdin("no\n")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("already exists").and(predicate::str::contains("Keeping the existing config file.")));
+    assert_eq!(std::fs::read_to_string(&config_path)?, "user_agent = \"ua\"\n");
+    Ok(())
+}
+
+#[test]
+fn test_invalid_where_expression_is_reported_before_login() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = tempdir()?;
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(temp_dir.path())
+        .args(["--where", "subreddit = rust and score <"])
+        .assert()
+        .failure()
+        .stderr(
+            predicate::str::contains("Error parsing --where: expected a value, the expression ended")
+                .and(predicate::str::contains("Failed to locate config file").not()),
+        );
+    Ok(())
+}
+
+#[test]
+fn test_apply_policy_validates_rules_before_login() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = tempdir()?;
+    std::fs::write(
+        temp_dir.path().join("rules.toml"),
+        "[[rule]]\nname = \"old comments\"\nsource = [\"comments\"]\nmin_age = \"90 days\"\naction = \"overwrite\"\n",
+    )?;
+
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(temp_dir.path())
+        .args(["apply-policy", "rules.toml"])
+        .assert()
+        .failure()
+        .stderr(
+            predicate::str::contains("rule \\\"old comments\\\": `action = \\\"overwrite\\\"` needs `text`")
+                .and(predicate::str::contains("Failed to locate config file").not()),
+        );
+    Ok(())
+}
+
+#[test]
+fn test_run_options_are_accepted_after_apply_policy() -> Result<(), Box<dyn std::error::Error>> {
+    let temp_dir = tempdir()?;
+    std::fs::write(
+        temp_dir.path().join("rules.toml"),
+        "[[rule]]\nname = \"old comments\"\nsource = [\"comments\"]\naction = \"delete\"\n",
+    )?;
+
+    // Stops at the missing config file, after the arguments were accepted
+    let mut cmd = Command::cargo_bin(env!("CARGO_PKG_NAME"))?;
+    cmd.current_dir(temp_dir.path())
+        .env("HOME", temp_dir.path())
+        .env("XDG_CONFIG_HOME", temp_dir.path().join("config"))
+        .env("XDG_DATA_HOME", temp_dir.path().join("data"))
+        .args(["apply-policy", "rules.toml", "--yes", "--dry-run", "--protect-gilded", "--protect-score-above", "100"])
+        .assert()
+        .failure()
+        .stderr(
+            predicate::str::contains("Failed to locate config file")
+                .and(predicate::str::contains("unexpected argument").not()),
+        );
+    Ok(())
+}